@@ -0,0 +1,21 @@
+//! Times parsing a large board, one tile at a time and as a single line. Run with `cargo bench`
+use std::hint::black_box;
+use std::time::Instant;
+use rummikub::{RuleSet, State, Tile};
+
+fn main() {
+    let deck = RuleSet::standard().deck();
+    let tokens: Vec<String> = (0..1000).flat_map(|_| deck.iter()).map(|(t, _)| t.to_string().trim_end().to_string()).collect();
+    let line = tokens.join(" ");
+
+    let start = Instant::now();
+    let parsed = tokens.iter().filter(|t| black_box(Tile::from_str(black_box(t))).is_ok()).count();
+    let elapsed = start.elapsed();
+    println!("Tile::from_str: {parsed} tiles in {elapsed:?} ({:.0} tiles/ms)", parsed as f64 / elapsed.as_secs_f64() / 1000.0);
+
+    let mut state = State::with_capacity(tokens.len(), 0);
+    let start = Instant::now();
+    state.add_board_line(black_box(&line)).unwrap();
+    let elapsed = start.elapsed();
+    println!("State::add_board_line: {} tiles in {elapsed:?} ({:.0} tiles/ms)", state.board().len(), state.board().len() as f64 / elapsed.as_secs_f64() / 1000.0);
+}
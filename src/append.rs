@@ -0,0 +1,140 @@
+use crate::{solve_with_config, Board, Colour, Meld, MeldKind, Objective, RuleSet, Solution, SolverConfig, State, Tile, TileMultiset};
+
+/// Plays as many hand tiles as possible without breaking up `board_melds`: tiles can only be added to the ends of
+/// runs, to groups, or laid down as new melds. Returns the solution and how many tiles were appended to existing
+/// melds. `None` if `board_melds` aren't the tiles on `state`'s board. The same as solving with
+/// `Objective::AppendOnly` first
+pub fn solve_append_only(state: &State, board_melds: &[Meld]) -> Option<(Solution, usize)> {
+    let board: Board = board_melds.iter().flat_map(|m| m.tiles().iter().copied()).collect();
+    if board != *state.board() { return None; }
+
+    let config = SolverConfig {
+        objectives: vec![Objective::AppendOnly, Objective::MaxTilesPlayed],
+        board_melds: board_melds.to_vec(),
+        ..SolverConfig::default()
+    };
+    let solution = solve_with_config(state, &config)?;
+    let appended = solution.appended()?;
+    Some((solution, appended))
+}
+
+/// `meld` laid out so that `board` sits in it unchanged, if it holds all of `board`'s tiles and only adds to a
+/// run's ends or a group's colours. A meld with one real tile can be either kind
+pub(crate) fn laid_around(board: &Meld, meld: &Meld, rules: &RuleSet) -> Option<Meld> {
+    let board_tiles = board.joker_view();
+    let mut left: TileMultiset = meld.tiles().iter().collect();
+    if !board_tiles.iter().all(|t| left.remove(t)) { return None; }
+    let single = meld.tiles().iter().filter(|t| !t.is_joker()).count() == 1;
+
+    if board.kind() == MeldKind::Group {
+        if meld.kind() == MeldKind::Group { return Some(meld.clone()); }
+        if !single || meld.tiles().len() > rules.max_group_size.min(Colour::ALL.len()) { return None; }
+        let mut tiles = board_tiles;
+        tiles.resize(meld.tiles().len(), Tile::Joker);
+        return Some(Meld::new_unchecked(tiles, MeldKind::Group, rules));
+    }
+
+    if meld.kind() != MeldKind::Run && !single { return None; }
+    let (colour, low, high) = board.run_range()?;
+    // Added tiles can't take a number the board meld already covers, even one of its jokers
+    let added: Vec<u8> = left.iter().filter(|(t, _)| !t.is_joker()).flat_map(|(t, n)| std::iter::repeat_n(t.number().unwrap(), n)).collect();
+    if added.iter().any(|n| (low..=high).contains(n)) { return None; }
+
+    let len = meld.tiles().len() as i32;
+    let needed_low = added.iter().copied().chain([low]).min().unwrap() as i32;
+    let needed_high = added.iter().copied().chain([high]).max().unwrap() as i32;
+    // Spare jokers go above the meld, as far as the numbers allow
+    let start = needed_low.min(rules.max_number as i32 + 1 - len);
+    if start < 1 || start + len - 1 < needed_high { return None; }
+
+    let tiles = (start..start + len).map(|n| {
+        let tile = Tile::Normal(colour, n as u8);
+        if added.contains(&(n as u8)) || (low as i32..=high as i32).contains(&n) && board_tiles.contains(&tile) { tile } else { Tile::Joker }
+    }).collect();
+    Some(Meld::new_unchecked(tiles, MeldKind::Run, rules))
+}
+
+/// For each of `board_melds`, the index of the meld in `melds` it sits in unchanged, with as many matched as
+/// possible and no meld used twice
+pub(crate) fn match_board_melds(board_melds: &[&Meld], melds: &[Meld], rules: &RuleSet) -> Vec<Option<usize>> {
+    let fits: Vec<Vec<usize>> = board_melds.iter()
+        .map(|b| (0..melds.len()).filter(|&m| laid_around(b, &melds[m], rules).is_some()).collect())
+        .collect();
+    let mut owner: Vec<Option<usize>> = vec![None; melds.len()];
+
+    // Augmenting paths, moving earlier board melds to other melds where that frees one up
+    fn assign(b: usize, fits: &[Vec<usize>], owner: &mut [Option<usize>], seen: &mut [bool]) -> bool {
+        for &m in &fits[b] {
+            if seen[m] { continue; }
+            seen[m] = true;
+            if owner[m].is_none_or(|other| assign(other, fits, owner, seen)) {
+                owner[m] = Some(b);
+                return true;
+            }
+        }
+        false
+    }
+    for b in 0..board_melds.len() { assign(b, &fits, &mut owner, &mut vec![false; melds.len()]); }
+
+    let mut matched = vec![None; board_melds.len()];
+    for (m, b) in owner.iter().enumerate() {
+        if let Some(b) = b { matched[*b] = Some(m); }
+    }
+    matched
+}
+
+/// Lays each meld holding one of `board_melds` out around it, returning how many tiles were added to them
+pub(crate) fn lay_out_appended(board_melds: &[Meld], melds: &mut [Meld], rules: &RuleSet) -> usize {
+    let matched = match_board_melds(&board_melds.iter().collect::<Vec<_>>(), melds, rules);
+    let mut appended = 0;
+    for (board, m) in board_melds.iter().zip(matched) {
+        let Some(m) = m else { continue; };
+        melds[m] = laid_around(board, &melds[m], rules).unwrap();
+        appended += melds[m].tiles().len() - board.tiles().len();
+    }
+    appended
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{position, tiles};
+    use crate::solve;
+
+    #[test]
+    fn board_melds_stay_whole() {
+        let state = position("r1 r2 r3 r4 r5 r6", "r3 r4");
+        assert_eq!(solve(&state).unwrap().played().len(), 2);
+
+        let layout = vec![Meld::run(tiles("r1 r2 r3 r4 r5 r6")).unwrap()];
+        let (solution, appended) = solve_append_only(&state, &layout).unwrap();
+        assert!(solution.played().is_empty());
+        assert_eq!(appended, 0);
+    }
+
+    #[test]
+    fn jokers_keep_their_place() {
+        let state = position("r5 j r7", "r6 r8");
+        let layout = vec![Meld::run(tiles("r5 j r7")).unwrap()];
+        let (solution, appended) = solve_append_only(&state, &layout).unwrap();
+        assert_eq!(solution.played(), tiles("r8"));
+        assert_eq!(appended, 1);
+        assert_eq!(solution.melds()[0].tiles(), tiles("r5 j r7 r8"));
+    }
+
+    #[test]
+    fn combines_with_other_objectives() {
+        let state = position("b5 y5 x5 r1 r2 r3", "r5 r4 b6 b7 j");
+        let config = SolverConfig {
+            objectives: vec![Objective::AppendOnly, Objective::KeepPreferred, Objective::MaxTilesPlayed],
+            board_melds: vec![Meld::group(tiles("b5 y5 x5")).unwrap(), Meld::run(tiles("r1 r2 r3")).unwrap()],
+            prefer_keep: tiles("j"),
+            ..SolverConfig::default()
+        };
+        let solution = solve_with_config(&state, &config).unwrap();
+        // r5 joins the group and r4 the run, but b6 b7 need the joker, which is kept
+        assert_eq!(solution.played(), tiles("r4 r5"));
+        assert_eq!(solution.appended(), Some(2));
+        assert!(solution.verify(&state).is_ok());
+    }
+}
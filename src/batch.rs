@@ -0,0 +1,72 @@
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::json::Json;
+use crate::{solve_budgeted, SolveError, SolveEvent, SolverConfig, State};
+
+/// How a `solve_batch` run went, counting non-empty lines
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct BatchSummary {
+    pub solved: usize,
+    pub unsolved: usize,
+    /// Lines that couldn't be read as a position
+    pub errors: usize
+}
+
+/// Solves each line of `input`, a position in `State::from_notation` form, writing one JSON object per line to
+/// `output`: `{"line":1,"solution":{...},"nodes":812,"elapsed_ms":0.4,"budget_exhausted":false,
+/// "timed_out":false}`, with a null solution if there's none, or `{"line":2,"error":"..."}` if the line can't be read. Each
+/// line's board melds are used as `config.board_melds`
+pub fn solve_batch(input: impl BufRead, mut output: impl Write, config: &SolverConfig) -> io::Result<BatchSummary> {
+    // Nodes and time taken for the latest solve
+    let finished = Arc::new(Mutex::new((0, Duration::ZERO)));
+    let mut config = config.clone();
+    let on_event = config.on_event.take();
+    let stats = finished.clone();
+    config.on_event = Some(Arc::new(move |event| {
+        match &event {
+            SolveEvent::Finished { nodes, elapsed, .. } => *stats.lock().unwrap() = (*nodes, *elapsed),
+            SolveEvent::Started { .. } | SolveEvent::Improved { .. } | SolveEvent::BudgetExhausted { .. } | SolveEvent::TimedOut { .. } => {}
+        }
+        if let Some(on_event) = &on_event { on_event(event); }
+    }));
+
+    let mut summary = BatchSummary::default();
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+
+        let mut fields = vec![("line".to_string(), Json::Number((i + 1) as f64))];
+        match State::from_notation(&line) {
+            Ok((state, melds)) => {
+                config.board_melds = melds;
+                // Positions turned away before searching have no events
+                *finished.lock().unwrap() = (0, Duration::ZERO);
+                let (solution, exhausted, timed_out) = match solve_budgeted(&state, &config) {
+                    Ok(solution) => (Some(solution), false, false),
+                    Err(SolveError::NoArrangement | SolveError::JokersOnly) => (None, false, false),
+                    Err(SolveError::BudgetExhausted { best }) => (best, true, false),
+                    Err(SolveError::TimedOut { best }) => (best, true, true)
+                };
+                let (nodes, elapsed) = *finished.lock().unwrap();
+                if solution.is_some() { summary.solved += 1; } else { summary.unsolved += 1; }
+                fields.extend([
+                    ("solution".to_string(), solution.map_or(Json::Null, |s| s.to_json_value())),
+                    ("nodes".to_string(), Json::Number(nodes as f64)),
+                    ("elapsed_ms".to_string(), Json::Number(elapsed.as_secs_f64() * 1000.0)),
+                    ("budget_exhausted".to_string(), Json::Bool(exhausted)),
+                    ("timed_out".to_string(), Json::Bool(timed_out))
+                ]);
+            },
+            Err(e) => {
+                summary.errors += 1;
+                fields.push(("error".to_string(), Json::String(e.to_string())));
+            }
+        }
+
+        let mut out = String::new();
+        Json::Object(fields).write(&mut out);
+        writeln!(output, "{out}")?;
+    }
+    Ok(summary)
+}
@@ -0,0 +1,49 @@
+use std::sync::LazyLock;
+use crate::Tile;
+
+/// How tiles are written out, and read back in
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DisplayConfig {
+    pub joker_symbol: String,
+    /// Tiles on each line of `Tile::format_list_with`
+    pub tiles_per_row: usize,
+    /// Writes tiles with `Tile::to_emoji`. Output in this form can't be parsed back
+    pub emoji: bool,
+    /// Right-aligns numbers to two digits so that lists line up in columns, e.g. `r 5` above `r12`
+    pub pad_numbers: bool,
+    /// Reorders lists before writing them out. `None` keeps them as given, e.g. melds in play order
+    pub sort_order: Option<SortOrder>
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig { joker_symbol: Tile::JOKER_CHAR.to_string(), tiles_per_row: 10, emoji: false, pad_numbers: false, sort_order: None }
+    }
+}
+
+/// `DisplayConfig::default()`, built once for `Tile::to_string` and the other writers that don't take a config
+pub(crate) static DEFAULT_CONFIG: LazyLock<DisplayConfig> = LazyLock::new(DisplayConfig::default);
+
+/// Ways of ordering tiles for display. Stored lists always stay colour first, which is `Tile`'s `Ord`
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum SortOrder {
+    #[default]
+    ColourFirst,
+    /// All the tiles of a number together, e.g. `r5 b5 y5 x5 r6 ..`
+    NumberFirst
+}
+
+impl SortOrder {
+    /// Sorting by this key gives the order. Jokers always sort last
+    pub fn key(self, tile: &Tile) -> (u8, u8) {
+        match (self, tile) {
+            (_, Tile::Joker) => (u8::MAX, u8::MAX),
+            (SortOrder::ColourFirst, Tile::Normal(c, n)) => (*c as u8, *n),
+            (SortOrder::NumberFirst, Tile::Normal(c, n)) => (*n, *c as u8)
+        }
+    }
+
+    pub fn sort(self, tiles: &mut [Tile]) {
+        tiles.sort_by_key(|t| self.key(t));
+    }
+}
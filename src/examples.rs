@@ -0,0 +1,58 @@
+//! Named positions for docs, tests and benchmarks
+
+use crate::State;
+#[cfg(test)]
+use crate::Tile;
+
+/// `board` and `hand` read as tile lines
+pub(crate) fn position(board: &str, hand: &str) -> State {
+    let mut state = State::new();
+    state.add_board_line(board).unwrap();
+    state.add_hand_line(hand).unwrap();
+    state
+}
+
+/// The tiles of `line`, for tests
+#[cfg(test)]
+pub(crate) fn tiles(line: &str) -> Vec<Tile> {
+    line.split_whitespace().map(|t| Tile::from_str(t).unwrap()).collect()
+}
+
+/// The board profiled from `main`
+pub fn sample() -> State {
+    position("r1 r4 r12 b1 b4 b12 y1 y2 y3 x1 x1 x2 x3 x4 x4 x6 x8 x12 j", "")
+}
+
+/// The known-good `Solution::format` of `sample`, so solver changes can't silently alter it
+pub const SAMPLE_SOLUTION: &str = "r1 b1 x1 \nr4 b4 x4 \nr12 b12 x12 \ny1 y2 y3 \nx1 x2 x3 x4 \nx6 j x8 \n";
+
+/// `sample` with a full hand to play from
+pub fn sample_with_hand() -> State {
+    position("r1 r4 r12 b1 b4 b12 y1 y2 y3 x1 x1 x2 x3 x4 x4 x6 x8 x12 j", "y6 y6 b9 x9 r7 y7 r1 r2 x8 x12 r13")
+}
+
+/// Both jokers on the board, each standing in for a tile that's in the hand
+pub fn joker_heavy() -> State {
+    position("r3 j r5 b7 y7 j", "r4 x7 b8 y1")
+}
+
+/// A first turn: nothing on the board and a fresh 14 tile hand
+pub fn empty_board_opening() -> State {
+    position("", "r10 r11 r12 b13 y13 x13 j r2 b5 y8 x1 x4 b9 y2")
+}
+
+/// A board that can't be split into valid melds
+pub fn unsolvable() -> State {
+    position("r1 r2 r4 b7 y9 x13", "y1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solve;
+
+    #[test]
+    fn sample_solution() {
+        assert_eq!(solve(&sample()).unwrap().format(), SAMPLE_SOLUTION);
+    }
+}
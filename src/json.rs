@@ -0,0 +1,338 @@
+//! Just enough JSON to write out and read back melds and solutions
+
+use std::fmt::Write;
+use crate::{Meld, MeldKind, ResolvedTile, Solution, Tile};
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self { Json::String(s) => Some(s), _ => None }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self { Json::Array(a) => Some(a), _ => None }
+    }
+
+    pub(crate) fn write(&self, out: &mut String) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => write!(out, "{b}").unwrap(),
+            Json::Number(n) => write!(out, "{n}").unwrap(),
+            Json::String(s) => write_string(s, out),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    item.write(out);
+                }
+                out.push(']');
+            },
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    write_string(key, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Json, &'static str> {
+        let mut parser = Parser { chars: text.chars().collect(), i: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.i != parser.chars.len() { return Err("Trailing characters after JSON"); }
+        Ok(value)
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+struct Parser {
+    chars: Vec<char>,
+    i: usize
+}
+
+impl Parser {
+    fn whitespace(&mut self) {
+        while self.chars.get(self.i).is_some_and(|c| c.is_whitespace()) { self.i += 1; }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), &'static str> {
+        self.whitespace();
+        if self.chars.get(self.i) != Some(&c) { return Err("Unexpected character in JSON"); }
+        self.i += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, &'static str> {
+        for c in word.chars() {
+            if self.chars.get(self.i) != Some(&c) { return Err("Invalid JSON literal"); }
+            self.i += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, &'static str> {
+        self.whitespace();
+        match self.chars.get(self.i) {
+            None => Err("Unexpected end of JSON"),
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => {
+                self.i += 1;
+                let mut items = Vec::new();
+                self.whitespace();
+                if self.chars.get(self.i) == Some(&']') { self.i += 1; return Ok(Json::Array(items)); }
+                loop {
+                    items.push(self.value()?);
+                    self.whitespace();
+                    match self.chars.get(self.i) {
+                        Some(',') => self.i += 1,
+                        Some(']') => { self.i += 1; return Ok(Json::Array(items)); },
+                        _ => return Err("Expected ',' or ']' in JSON array")
+                    }
+                }
+            },
+            Some('{') => {
+                self.i += 1;
+                let mut fields = Vec::new();
+                self.whitespace();
+                if self.chars.get(self.i) == Some(&'}') { self.i += 1; return Ok(Json::Object(fields)); }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    self.whitespace();
+                    match self.chars.get(self.i) {
+                        Some(',') => self.i += 1,
+                        Some('}') => { self.i += 1; return Ok(Json::Object(fields)); },
+                        _ => return Err("Expected ',' or '}' in JSON object")
+                    }
+                }
+            },
+            Some(_) => {
+                let start = self.i;
+                while self.chars.get(self.i).is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) { self.i += 1; }
+                let number: String = self.chars[start..self.i].iter().collect();
+                number.parse().map(Json::Number).map_err(|_| "Invalid JSON value")
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        if self.chars.get(self.i) != Some(&'"') { return Err("Expected a JSON string"); }
+        self.i += 1;
+
+        let mut s = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.i) else { return Err("Unterminated JSON string"); };
+            self.i += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let Some(&e) = self.chars.get(self.i) else { return Err("Unterminated JSON string"); };
+                    self.i += 1;
+                    match e {
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.chars.get(self.i..self.i + 4).ok_or("Invalid JSON escape")?.iter().collect();
+                            self.i += 4;
+                            let code = u32::from_str_radix(&hex, 16).map_err(|_| "Invalid JSON escape")?;
+                            s.push(char::from_u32(code).ok_or("Invalid JSON escape")?);
+                        },
+                        e => s.push(e)
+                    }
+                },
+                c => s.push(c)
+            }
+        }
+    }
+}
+
+fn tile_to_json(tile: &Tile) -> Json {
+    Json::String(tile.to_string().trim_end().to_string())
+}
+
+fn tile_from_json(json: &Json) -> Result<Tile, &'static str> {
+    Tile::from_str(json.as_str().ok_or("Expected a tile")?)
+}
+
+fn tiles_to_json(tiles: &[Tile]) -> Json {
+    Json::Array(tiles.iter().map(tile_to_json).collect())
+}
+
+fn tiles_from_json(json: Option<&Json>) -> Result<Vec<Tile>, &'static str> {
+    json.and_then(Json::as_array).ok_or("Expected a list of tiles")?.iter().map(tile_from_json).collect()
+}
+
+impl ResolvedTile {
+    fn to_json_value(&self) -> Json {
+        Json::Object(vec![
+            ("tile".to_string(), tile_to_json(&self.tile)),
+            ("colour".to_string(), Json::String(self.colour.get_char().to_string())),
+            ("number".to_string(), Json::Number(self.number as f64)),
+            ("alternatives".to_string(), Json::Array(
+                self.alternatives.iter().map(|(c, n)| tile_to_json(&Tile::Normal(*c, *n))).collect()
+            ))
+        ])
+    }
+
+    fn from_json_value(json: &Json) -> Result<ResolvedTile, &'static str> {
+        let tile = tile_from_json(json.get("tile").ok_or("Missing tile")?)?;
+        // The value it stands for is written like a tile without its number, e.g. `r`
+        let colour = json.get("colour").and_then(Json::as_str).ok_or("Missing colour")?;
+        let Ok(Tile::Normal(colour, _)) = Tile::from_str(format!("{colour}1")) else { return Err("Invalid colour"); };
+        let number = match json.get("number") {
+            Some(Json::Number(n)) if n.fract() == 0.0 && (1.0..=Meld::MAX_NUMBER as f64).contains(n) => *n as u8,
+            _ => return Err("Invalid number")
+        };
+        let alternatives = tiles_from_json(json.get("alternatives"))?.into_iter()
+            .map(|t| match t { Tile::Normal(c, n) => Ok((c, n)), Tile::Joker => Err("A joker can't stand for a joker") })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ResolvedTile { tile, colour, number, alternatives })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.to_json_value().write(&mut out);
+        out
+    }
+
+    pub fn from_json(text: &str) -> Result<ResolvedTile, &'static str> {
+        ResolvedTile::from_json_value(&Json::parse(text)?)
+    }
+}
+
+impl Meld {
+    fn to_json_value(&self) -> Json {
+        let kind = match self.kind() { MeldKind::Run => "run", MeldKind::Group => "group" };
+        Json::Object(vec![
+            ("kind".to_string(), Json::String(kind.to_string())),
+            ("tiles".to_string(), Json::Array(self.resolve().iter().map(ResolvedTile::to_json_value).collect()))
+        ])
+    }
+
+    /// Reads back the melds `to_json` writes. Only the tiles and kind are used - the jokers' values follow
+    /// from the tiles' order
+    fn from_json_value(json: &Json) -> Result<Meld, &'static str> {
+        let tiles = json.get("tiles").and_then(Json::as_array).ok_or("Missing tiles")?.iter()
+            .map(|t| tile_from_json(t.get("tile").ok_or("Missing tile")?))
+            .collect::<Result<Vec<Tile>, _>>()?;
+
+        let meld = match json.get("kind").and_then(Json::as_str) {
+            Some("run") => Meld::run(tiles.clone()),
+            Some("group") => Meld::group(tiles.clone()),
+            _ => return Err("Invalid meld kind")
+        }.map_err(|_| "Invalid meld")?;
+        if meld.tiles() != tiles { return Err("Run tiles out of order"); }
+        Ok(meld)
+    }
+
+    /// Each tile with the value it stands for, e.g.
+    /// `{"kind":"run","tiles":[{"tile":"j","colour":"r","number":4,"alternatives":["r4","r7"]},...]}`
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.to_json_value().write(&mut out);
+        out
+    }
+
+    pub fn from_json(text: &str) -> Result<Meld, &'static str> {
+        Meld::from_json_value(&Json::parse(text)?)
+    }
+}
+
+impl Solution {
+    /// `{"melds":[...],"played":["r7",...],"hand":[...]}`, with melds as in `Meld::to_json`
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.to_json_value().write(&mut out);
+        out
+    }
+
+    pub(crate) fn to_json_value(&self) -> Json {
+        Json::Object(vec![
+            ("melds".to_string(), Json::Array(self.melds().iter().map(Meld::to_json_value).collect())),
+            ("played".to_string(), tiles_to_json(self.played())),
+            ("hand".to_string(), tiles_to_json(self.hand()))
+        ])
+    }
+
+    pub fn from_json(text: &str) -> Result<Solution, &'static str> {
+        let json = Json::parse(text)?;
+        let melds = json.get("melds").and_then(Json::as_array).ok_or("Missing melds")?.iter()
+            .map(Meld::from_json_value)
+            .collect::<Result<_, _>>()?;
+        Ok(Solution::new(melds, tiles_from_json(json.get("played"))?, tiles_from_json(json.get("hand"))?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Colour};
+    use crate::examples::position;
+
+    #[test]
+    fn solution_round_trip() {
+        let state = position("r4 j r6 b9 y9 x9 j", "r7 b2 b9");
+        let solution = solve(&state).unwrap();
+
+        let json = solution.to_json();
+        let read = Solution::from_json(&json).unwrap();
+        assert_eq!(read.melds(), solution.melds());
+        assert_eq!(read.played(), solution.played());
+        assert_eq!(read.hand(), solution.hand());
+        assert_eq!(read.to_json(), json);
+    }
+
+    #[test]
+    fn jokers_carry_their_value() {
+        let meld = Meld::run(vec![Tile::Normal(Colour::Red, 5), Tile::Joker, Tile::Normal(Colour::Red, 7)]).unwrap();
+        let json = meld.to_json();
+        assert!(json.contains(r#"{"tile":"j","colour":"r","number":6"#), "{json}");
+        assert_eq!(Meld::from_json(&json).unwrap(), meld);
+
+        let joker = &meld.resolve()[1];
+        assert_eq!(&ResolvedTile::from_json(&joker.to_json()).unwrap(), joker);
+    }
+}
@@ -0,0 +1,1122 @@
+mod solver;
+mod append;
+#[cfg(feature = "cli")]
+mod batch;
+mod display;
+mod solution;
+mod svg;
+pub mod examples;
+mod json;
+mod meld;
+mod metrics;
+mod moves;
+mod multiset;
+mod placement;
+mod region;
+#[cfg(feature = "cli")]
+mod repl;
+mod rules;
+mod scoring;
+mod simulate;
+mod tile_list;
+
+use std::collections::{vec_deque, BTreeSet, VecDeque};
+use std::fmt;
+use std::iter::Chain;
+use std::str::FromStr;
+use crate::display::DEFAULT_CONFIG;
+#[cfg(feature = "cli")]
+pub use crate::batch::{solve_batch, BatchSummary};
+pub use crate::solver::{solve, solve_budgeted, solve_constrained, solve_to_free, solve_with, solve_with_config, ConstraintError, Objective, SolveError, SolveEvent, SolveProgress, SolverConfig, TraceDecision, TraceNode};
+pub use crate::solution::{Solution, SolveStep, TileMove, VerifyError};
+pub use crate::display::{DisplayConfig, SortOrder};
+pub use crate::meld::{meld_ambiguity, AcceptResult, Meld, MeldAmbiguity, MeldError, MeldKind, ResolvedTile};
+pub use crate::metrics::SolveMetrics;
+pub use crate::moves::{validate_move, MoveError};
+pub use crate::multiset::{tile_counts, TileMultiset};
+pub use crate::placement::Placement;
+pub use crate::append::solve_append_only;
+pub use crate::region::{solve_regions, Region};
+#[cfg(feature = "cli")]
+pub use crate::repl::{run_interactive, InputSource, OutputSink};
+pub use crate::rules::RuleSet;
+pub use crate::scoring::{hand_penalty, score_round, ScoreResult};
+pub use crate::simulate::DrawStats;
+pub use crate::tile_list::{Board, Hand};
+
+/// The crate's version, e.g. `0.1.0`
+pub fn version() -> &'static str { env!("CARGO_PKG_VERSION") }
+
+/// What this build of the crate can do, see `capabilities`
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Capabilities {
+    /// Built with the `profiling` feature
+    pub profiling: bool,
+    /// Built with the `parallel` feature, so `State::simulate_draws` and `State::rank_draws` run across threads
+    pub parallel: bool,
+    /// Built for `wasm32`, which has no threads, so `parallel` is off there
+    pub wasm: bool
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        profiling: cfg!(feature = "profiling"),
+        parallel: cfg!(feature = "parallel") && !cfg!(target_arch = "wasm32"),
+        wasm: cfg!(target_arch = "wasm32")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum Colour {
+    Red,
+    Blue,
+    Yellow,
+    Black
+}
+
+impl Colour {
+    pub const RED_CHAR: char = 'r';
+    pub const BLUE_CHAR: char = 'b';
+    pub const YELLOW_CHAR: char = 'y';
+    pub const BLACK_CHAR: char = 'x';
+
+    pub const ALL: [Colour; 4] = [Colour::Red, Colour::Blue, Colour::Yellow, Colour::Black];
+
+    /// Every colour, in `Ord` order
+    pub fn all() -> impl Iterator<Item=Colour> { Colour::ALL.into_iter() }
+
+    pub const fn get_char(&self) -> char {
+        match &self {
+            Colour::Red => Colour::RED_CHAR,
+            Colour::Blue => Colour::BLUE_CHAR,
+            Colour::Yellow => Colour::YELLOW_CHAR,
+            Colour::Black => Colour::BLACK_CHAR
+        }
+    }
+
+    pub const fn get_emoji(&self) -> char {
+        match &self {
+            Colour::Red => '🔴',
+            Colour::Blue => '🔵',
+            Colour::Yellow => '🟡',
+            Colour::Black => '⚫'
+        }
+    }
+}
+
+/// Tiles order by colour then number, and jokers after every numbered tile. Sorted lists such as `Board` and
+/// `Hand` can rely on this to find their jokers at the end
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum Tile {
+    Normal(Colour, u8),
+    // Must stay the last variant - the derived `Ord` is what puts jokers last
+    Joker
+}
+
+impl Tile {
+    pub const JOKER_CHAR: &'static str = "j";
+    pub const NUMBERS: usize = 13;
+    pub const JOKER_ORDINAL: u8 = 4 * Tile::NUMBERS as u8;
+    /// Number of distinct tiles, jokers included
+    pub const KINDS: usize = Tile::JOKER_ORDINAL as usize + 1;
+
+    pub fn is_joker(&self) -> bool {
+        match &self {
+            Tile::Joker => true,
+            Tile::Normal(_, _) => false
+        }
+    }
+
+    pub fn colour(&self) -> Option<Colour> {
+        match &self {
+            Tile::Normal(c, _) => Some(*c),
+            Tile::Joker => None
+        }
+    }
+
+    pub fn number(&self) -> Option<u8> {
+        match &self {
+            Tile::Normal(_, n) => Some(*n),
+            Tile::Joker => None
+        }
+    }
+
+    pub fn is_colour(&self, colour: Colour) -> bool { self.colour() == Some(colour) }
+
+    pub fn is_number(&self, number: u8) -> bool { self.number() == Some(number) }
+
+    /// The tiles either side of this one in a run, e.g. `r4` and `r6` for `r5`. None for a joker
+    pub fn neighbours(&self) -> Vec<Tile> {
+        self.neighbours_with_rules(&RuleSet::standard(), false)
+    }
+
+    /// As `neighbours`, within `rules.max_number`. A joker sits next to every numbered tile if `joker_matches_all`
+    pub fn neighbours_with_rules(&self, rules: &RuleSet, joker_matches_all: bool) -> Vec<Tile> {
+        match self {
+            Tile::Joker if joker_matches_all => Colour::all()
+                .flat_map(|c| (1..=rules.max_number).map(move |n| Tile::Normal(c, n)))
+                .collect(),
+            Tile::Joker => Vec::new(),
+            Tile::Normal(c, n) => [n.checked_sub(1), n.checked_add(1)].into_iter()
+                .flatten()
+                .filter(|n| (1..=rules.max_number).contains(n))
+                .map(|n| Tile::Normal(*c, n))
+                .collect()
+        }
+    }
+
+    /// Whether this tile can come straight after `other` in a run. A joker can follow, or be followed by, any tile
+    /// that leaves room for it
+    pub fn can_follow(&self, other: &Tile) -> bool {
+        match (&self, other) {
+            (Tile::Normal(c, n), Tile::Normal(other_c, other_n)) => c == other_c && *n == other_n + 1,
+            (Tile::Joker, Tile::Normal(_, other_n)) => *other_n < Meld::MAX_NUMBER,
+            (Tile::Normal(_, n), Tile::Joker) => *n > 1,
+            (Tile::Joker, Tile::Joker) => true
+        }
+    }
+
+    /// A dense index following `Ord`, with jokers last
+    pub fn ordinal(&self) -> u8 {
+        match &self {
+            Tile::Normal(c, n) => *c as u8 * Tile::NUMBERS as u8 + n - 1,
+            Tile::Joker => Tile::JOKER_ORDINAL
+        }
+    }
+
+    pub fn from_ordinal(ordinal: u8) -> Option<Tile> {
+        let colour = match ordinal / Tile::NUMBERS as u8 {
+            0 => Colour::Red,
+            1 => Colour::Blue,
+            2 => Colour::Yellow,
+            3 => Colour::Black,
+            _ => return if ordinal == Tile::JOKER_ORDINAL { Some(Tile::Joker) } else { None }
+        };
+        Some(Tile::Normal(colour, ordinal % Tile::NUMBERS as u8 + 1))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str<U: AsRef<str>>(string: U) -> Result<Tile, TileParseError> {
+        // Building the default `DisplayConfig` would allocate its joker symbol on every call
+        Tile::parse(string.as_ref(), Tile::JOKER_CHAR)
+    }
+
+    /// Parses a tile, taking the joker to be written as `config.joker_symbol`
+    pub fn from_str_with<U: AsRef<str>>(string: U, config: &DisplayConfig) -> Result<Tile, TileParseError> {
+        Tile::parse(string.as_ref(), &config.joker_symbol)
+    }
+
+    /// Parses a tile where a joker can carry a numeric label to tell it apart, e.g. `j2` is `(Tile::Joker,
+    /// Some(2))`. Other tiles and a bare `j` have no label
+    pub fn from_str_labelled<U: AsRef<str>>(string: U) -> Result<(Tile, Option<u16>), TileParseError> {
+        Tile::parse_labelled(string.as_ref(), Tile::JOKER_CHAR)
+    }
+
+    /// As `from_str_labelled`, taking the joker to be written as `config.joker_symbol`
+    pub fn from_str_labelled_with<U: AsRef<str>>(string: U, config: &DisplayConfig) -> Result<(Tile, Option<u16>), TileParseError> {
+        Tile::parse_labelled(string.as_ref(), &config.joker_symbol)
+    }
+
+    fn parse_labelled(string: &str, joker_symbol: &str) -> Result<(Tile, Option<u16>), TileParseError> {
+        if let Some(label) = Tile::joker_label(string, joker_symbol) { return Ok((Tile::Joker, Some(label))); }
+        Tile::parse(string, joker_symbol).map(|t| (t, None))
+    }
+
+    /// The label of a joker written as its symbol followed by digits
+    fn joker_label(string: &str, joker_symbol: &str) -> Option<u16> {
+        let label = string.strip_prefix(joker_symbol)?;
+        if !label.starts_with(|c: char| c.is_ascii_digit()) { return None; }
+        u16::from_str(label).ok()
+    }
+
+    fn parse(string: &str, joker_symbol: &str) -> Result<Tile, TileParseError> {
+        if string == joker_symbol || Tile::joker_label(string, joker_symbol).is_some() { return Ok(Tile::Joker); }
+        match string.len() {
+            0 => Err("No string"),
+            1 => Err("Not joker"),
+            _ => {
+                let mut chars = string.chars();
+                Ok(Tile::Normal(
+                    match chars.next().unwrap() {
+                        Colour::RED_CHAR => Colour::Red,
+                        Colour::BLUE_CHAR => Colour::Blue,
+                        Colour::YELLOW_CHAR => Colour::Yellow,
+                        Colour::BLACK_CHAR => Colour::Black,
+                        _ => return Err("Invalid colour")
+                    },
+                    // The rest of the string after the colour, without copying it
+                    u8::from_str(chars.as_str())
+                        .map_or_else(
+                            |_| Err("Invalid number"),
+                            |n| { if (1..=13).contains(&n) { Ok(n) } else { Err("Number out of range") } },
+                        )?
+                ))
+            },
+        }
+    }
+
+    /// Parses the number-first form some other apps export, e.g. `12b`, with `j`, `joker` or `wild` for a joker
+    pub fn from_str_number_first<U: AsRef<str>>(string: U) -> Result<Tile, TileParseError> {
+        let string = string.as_ref();
+        if ["j", "joker", "wild"].iter().any(|j| string.eq_ignore_ascii_case(j)) { return Ok(Tile::Joker); }
+
+        let Some(colour) = string.chars().last() else { return Err("No string"); };
+        let number = &string[..string.len() - colour.len_utf8()];
+        if number.is_empty() { return Err("Not joker"); }
+        // Would otherwise read as a labelled joker
+        if Tile::JOKER_CHAR.starts_with(colour) { return Err("Joker with a number"); }
+        Tile::from_str(format!("{colour}{number}"))
+    }
+
+    /// Checks a tile that is still being typed, e.g. `r` is incomplete and `r14` is invalid
+    pub fn validate_partial(string: &str) -> PartialResult {
+        if string.is_empty() { return PartialResult::Incomplete; }
+        if let Ok(t) = Tile::from_str(string) { return PartialResult::Complete(t); }
+
+        let mut chars = string.chars();
+        match chars.next().unwrap() {
+            Colour::RED_CHAR | Colour::BLUE_CHAR | Colour::YELLOW_CHAR | Colour::BLACK_CHAR => {},
+            _ if Tile::JOKER_CHAR.starts_with(string) => return PartialResult::Incomplete,
+            _ if string.starts_with(Tile::JOKER_CHAR) => return PartialResult::Invalid("Not joker"),
+            _ => return PartialResult::Invalid("Invalid colour")
+        }
+
+        // Only leading zeros (or nothing) can still be followed by a valid number
+        let mut n: u32 = 0;
+        for c in chars {
+            match c.to_digit(10) {
+                Some(d) => n = n * 10 + d,
+                None => return PartialResult::Invalid("Invalid number")
+            }
+            if n > 13 { return PartialResult::Invalid("Number out of range"); }
+        }
+
+        PartialResult::Incomplete
+    }
+
+    /// Every way of finishing a tile that is still being typed, e.g. `r1` gives `r1` and `r10` to `r13`. With
+    /// nothing typed yet, the colours and the joker
+    pub fn complete_partial(string: &str) -> Vec<String> {
+        if string.is_empty() {
+            let mut options: Vec<String> = Colour::all().map(|c| c.get_char().to_string()).collect();
+            options.push(Tile::JOKER_CHAR.to_string());
+            return options;
+        }
+        if let PartialResult::Invalid(_) = Tile::validate_partial(string) { return Vec::new(); }
+
+        (0..Tile::KINDS as u8)
+            .map(|o| Tile::from_ordinal(o).unwrap().to_string().trim_end().to_string())
+            .filter(|t| t.starts_with(string))
+            .collect()
+    }
+
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        self.to_string_with(&DEFAULT_CONFIG)
+    }
+
+    pub const JOKER_EMOJI: char = '⭐';
+
+    /// Like `to_string` but with a coloured marker in place of the colour letter, e.g. `🔴5 `
+    pub fn to_emoji(&self) -> String {
+        match &self {
+            Tile::Joker => format!("{} ", Tile::JOKER_EMOJI),
+            Tile::Normal(colour, number) => format!("{}{} ", colour.get_emoji(), number)
+        }
+    }
+
+    pub fn to_string_with(&self, config: &DisplayConfig) -> String {
+        // When padded, a joker takes up as many columns as a colour and a two digit number
+        let (number_width, joker_width) = if config.pad_numbers { (2, 3) } else { (0, 0) };
+        match &self {
+            Tile::Joker if config.emoji => format!("{:<joker_width$} ", Tile::JOKER_EMOJI),
+            Tile::Joker => format!("{:<joker_width$} ", config.joker_symbol),
+            Tile::Normal(colour, number) if config.emoji => format!("{}{:>number_width$} ", colour.get_emoji(), number),
+            Tile::Normal(colour, number) => format!("{}{:>number_width$} ", colour.get_char(), number)
+        }
+    }
+
+    pub fn format_list(list: &[Tile]) -> String {
+        Tile::format_list_with(list, &DEFAULT_CONFIG)
+    }
+
+    /// `format_list` with `per_row` tiles on each line instead of 10
+    pub fn format_list_width(list: &[Tile], per_row: usize) -> String {
+        Tile::format_list_with(list, &DisplayConfig { tiles_per_row: per_row, ..DisplayConfig::default() })
+    }
+
+    pub fn format_list_with(list: &[Tile], config: &DisplayConfig) -> String {
+        Tile::format_list_labelled(list, config, &[])
+    }
+
+    /// `format_list_with`, giving the list's jokers `labels` in order, e.g. `j1`
+    fn format_list_labelled(list: &[Tile], config: &DisplayConfig, labels: &[Option<u16>]) -> String {
+        let mut labels = labels.iter().copied();
+        let mut sorted;
+        let list = match config.sort_order {
+            Some(order) => { sorted = list.to_vec(); order.sort(&mut sorted); &sorted },
+            None => list
+        };
+        let mut string = String::with_capacity(list.len() * 3);
+
+        for ts in list.chunks(config.tiles_per_row.max(1)) {
+            for t in ts {
+                let label = if t.is_joker() { labels.next().flatten() } else { None };
+                match label {
+                    Some(label) => {
+                        let joker_width = if config.pad_numbers { 3 } else { 0 };
+                        let symbol = if config.emoji { Tile::JOKER_EMOJI.to_string() } else { config.joker_symbol.clone() };
+                        string += &format!("{:<joker_width$} ", format!("{symbol}{label}"));
+                    },
+                    None => string += &t.to_string_with(config)
+                }
+            }
+            string.push('\n');
+        }
+
+        string
+    }
+}
+
+/// Why a single tile failed to parse
+pub type TileParseError = &'static str;
+
+/// The first bad tile in a line, counting only non-empty tokens
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BatchParseError {
+    pub token_index: usize,
+    /// Where the token starts in the line, in characters
+    pub offset: usize,
+    pub token: String,
+    pub source: TileParseError
+}
+
+impl fmt::Display for BatchParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tile {} ('{}', at {}): {}", self.token_index + 1, self.token, self.offset, self.source)
+    }
+}
+
+/// A bad part of a position in meld notation, with the `|` separated segment it's in
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NotationError {
+    /// The error's offset is within the segment
+    InvalidTile { segment: String, error: BatchParseError },
+    InvalidMeld { segment: String, error: MeldError }
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::InvalidTile { segment, error } => write!(f, "In '{segment}': {error}"),
+            NotationError::InvalidMeld { segment, error } => write!(f, "'{segment}' isn't a meld: {error}")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PartialResult {
+    Complete(Tile),
+    Incomplete,
+    Invalid(&'static str)
+}
+
+#[derive(Debug, Clone)]
+pub struct State {
+    board: Board,
+    hand: Hand,
+    /// Board tiles played from the hand this turn, which can't go back to it
+    played_this_turn: TileMultiset,
+    /// The position when `begin` was called
+    checkpoint: Option<Box<State>>,
+    /// Labels given to the board's jokers as they were read, in order, e.g. `j1`. Jokers past the end are
+    /// unlabelled
+    joker_labels: Vec<Option<u16>>,
+    /// As `joker_labels`, for the hand's jokers
+    hand_joker_labels: Vec<Option<u16>>
+}
+
+impl State {
+    pub const BYTES_VERSION: u8 = 2;
+    /// Starts the hand in `from_notation`
+    pub const NOTATION_HAND: &'static str = "HAND:";
+
+    pub fn new() -> State {
+        State::from_parts(Board::new(), Hand::new())
+    }
+
+    /// Space for `board` and `hand` tiles without reallocating
+    pub fn with_capacity(board: usize, hand: usize) -> State {
+        State::from_parts(Board::with_capacity(board), Hand::with_capacity(hand))
+    }
+
+    pub(crate) fn from_parts(board: Board, hand: Hand) -> State {
+        State { board, hand, played_this_turn: TileMultiset::new(), checkpoint: None, joker_labels: Vec::new(), hand_joker_labels: Vec::new() }
+    }
+
+    /// Starts a turn's worth of tentative changes, to be kept with `commit` or undone with `rollback`. Beginning
+    /// again while one is open starts over from the current position
+    pub fn begin(&mut self) {
+        self.checkpoint = None;
+        self.checkpoint = Some(Box::new(self.clone()));
+    }
+
+    /// Keeps the changes since `begin` and ends the turn, so tiles played this turn become ordinary board tiles.
+    /// Returns whether a transaction was open, doing nothing if not
+    pub fn commit(&mut self) -> bool {
+        if self.checkpoint.take().is_none() { return false; }
+        self.played_this_turn = TileMultiset::new();
+        true
+    }
+
+    /// Puts the position back to how it was at `begin`, returning whether one was open
+    pub fn rollback(&mut self) -> bool {
+        let Some(checkpoint) = self.checkpoint.take() else { return false; };
+        *self = *checkpoint;
+        true
+    }
+
+    pub fn in_transaction(&self) -> bool { self.checkpoint.is_some() }
+
+    /// Moves a tile from the hand to the board, marking it as played this turn. Returns whether it was in hand
+    pub fn play_from_hand(&mut self, tile: Tile) -> bool {
+        if !self.hand.remove(&tile) { return false; }
+        if tile.is_joker() {
+            let label = take_labels(&mut self.hand_joker_labels, jokers_in(&self.hand) + 1, 1);
+            push_labels(&mut self.joker_labels, jokers_in(&self.board), label);
+        }
+        self.board.insert(tile);
+        self.played_this_turn.add(tile);
+        true
+    }
+
+    pub fn played_this_turn(&self) -> &TileMultiset { &self.played_this_turn }
+
+    pub fn add_to_board(&mut self, tile: Tile) {
+        self.add_many_to_board([tile])
+    }
+
+    pub fn add_to_hand(&mut self, tile: Tile) {
+        self.add_many_to_hand([tile])
+    }
+
+    /// Any jokers are unlabelled
+    pub fn add_many_to_board(&mut self, tiles: impl IntoIterator<Item=Tile>) {
+        self.add_labelled(true, tiles.into_iter().map(|t| (t, None)))
+    }
+
+    pub fn add_many_to_hand(&mut self, tiles: impl IntoIterator<Item=Tile>) {
+        self.add_labelled(false, tiles.into_iter().map(|t| (t, None)))
+    }
+
+    fn add_labelled(&mut self, to_board: bool, tiles: impl IntoIterator<Item=(Tile, Option<u16>)>) {
+        let mut labels = Vec::new();
+        let tiles = tiles.into_iter().inspect(|(t, label)| if t.is_joker() { labels.push(*label) }).map(|(t, _)| t);
+        if to_board {
+            self.board.extend(tiles);
+            let before = jokers_in(&self.board) - labels.len();
+            push_labels(&mut self.joker_labels, before, labels);
+        }
+        else {
+            self.hand.extend(tiles);
+            let before = jokers_in(&self.hand) - labels.len();
+            push_labels(&mut self.hand_joker_labels, before, labels);
+        }
+    }
+
+    fn parse_line(line: &str, joker_symbol: &str) -> Result<Vec<(Tile, Option<u16>)>, BatchParseError> {
+        line.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty())
+            .enumerate()
+            .map(|(token_index, token)| Tile::parse_labelled(token, joker_symbol).map_err(|source| {
+                let start = token.as_ptr() as usize - line.as_ptr() as usize;
+                BatchParseError { token_index, offset: line[..start].chars().count(), token: token.to_string(), source }
+            }))
+            .collect()
+    }
+
+    /// Parses a list of tiles separated by commas and/or whitespace, adding none of them if any is invalid. Jokers
+    /// can be labelled, e.g. `j1 j2`, to tell them apart in `format`
+    pub fn add_board_line(&mut self, line: &str) -> Result<(), BatchParseError> {
+        self.add_board_line_with(line, &DEFAULT_CONFIG)
+    }
+
+    /// As `add_board_line`, reading jokers written as `config.joker_symbol`
+    pub fn add_board_line_with(&mut self, line: &str, config: &DisplayConfig) -> Result<(), BatchParseError> {
+        let tiles = Self::parse_line(line, &config.joker_symbol)?;
+        self.add_labelled(true, tiles);
+        Ok(())
+    }
+
+    /// Labels given to the board's jokers, in the order they were added. `None` for a joker read as a bare `j`
+    pub fn joker_labels(&self) -> &[Option<u16>] { &self.joker_labels }
+
+    /// The labels for a solution's jokers: the board's, then those of the hand jokers it played
+    pub(crate) fn solution_labels(&self, played: &[Tile]) -> Vec<Option<u16>> {
+        let board = jokers_in(&self.board);
+        let played = played.iter().filter(|t| t.is_joker()).count();
+        if self.joker_labels.is_empty() && self.hand_joker_labels.is_empty() { return Vec::new(); }
+        let padded = |labels: &[Option<u16>], n| labels.iter().copied().chain(std::iter::repeat(None)).take(n).collect::<Vec<_>>();
+        let mut labels = padded(&self.joker_labels, board);
+        labels.extend(padded(&self.hand_joker_labels, played));
+        labels
+    }
+
+    /// As `joker_labels`, for the hand
+    pub fn hand_joker_labels(&self) -> &[Option<u16>] { &self.hand_joker_labels }
+
+    pub fn add_hand_line(&mut self, line: &str) -> Result<(), BatchParseError> {
+        self.add_hand_line_with(line, &DEFAULT_CONFIG)
+    }
+
+    /// As `add_hand_line`, reading jokers written as `config.joker_symbol`
+    pub fn add_hand_line_with(&mut self, line: &str, config: &DisplayConfig) -> Result<(), BatchParseError> {
+        let tiles = Self::parse_line(line, &config.joker_symbol)?;
+        self.add_labelled(false, tiles);
+        Ok(())
+    }
+
+    /// Reads a position with the board already split into melds, e.g. `r5 r6 r7 | b4 y4 x4 | HAND: r1 j`. Returns
+    /// the melds along with the position
+    pub fn from_notation(text: &str) -> Result<(State, Vec<Meld>), NotationError> {
+        let (board, hand) = text.split_once(State::NOTATION_HAND).unwrap_or((text, ""));
+
+        let mut state = State::new();
+        let mut melds = Vec::new();
+        for segment in board.split('|').map(str::trim).filter(|s| !s.is_empty()) {
+            let tiles = State::parse_line(segment, Tile::JOKER_CHAR)
+                .map_err(|error| NotationError::InvalidTile { segment: segment.to_string(), error })?;
+            let meld = Meld::new(tiles.iter().map(|(t, _)| *t).collect())
+                .map_err(|error| NotationError::InvalidMeld { segment: segment.to_string(), error })?;
+            state.add_labelled(true, tiles);
+            melds.push(meld);
+        }
+        let hand = hand.trim();
+        let hand = State::parse_line(hand, Tile::JOKER_CHAR).map_err(|error| NotationError::InvalidTile { segment: hand.to_string(), error })?;
+        state.add_labelled(false, hand);
+
+        Ok((state, melds))
+    }
+
+    /// Checks each set of tiles on its own, e.g. as a player lays the board out by hand
+    pub fn validate_melds(melds: &[Vec<Tile>]) -> Vec<Result<Meld, MeldError>> {
+        melds.iter().map(|m| Meld::new(m.clone())).collect()
+    }
+
+    /// Whether every tile of the meld, jokers included, is present on the board
+    pub fn board_contains_meld(&self, meld: &Meld) -> bool {
+        let mut board: TileMultiset = self.board.iter().collect();
+        meld.tiles().iter().all(|t| board.remove(t))
+    }
+
+    /// The most board tiles that can be arranged into valid melds, and the tiles that had to be left out
+    pub fn max_solvable_board_subset(&self) -> (Vec<Meld>, Vec<Tile>) {
+        solver::solve_board_subset(self)
+    }
+
+    /// Solves and moves the played tiles from the hand onto the board, returning the solution as a report of the
+    /// move. Leaves the position as it was and returns `None` if the board can't be arranged
+    pub fn solve_and_apply(&mut self, config: &SolverConfig) -> Option<Solution> {
+        let solution = solve_with_config(self, config)?;
+        let played_jokers = solution.played().iter().filter(|t| t.is_joker()).count();
+        let labels = take_labels(&mut self.hand_joker_labels, jokers_in(&self.hand), played_jokers);
+        push_labels(&mut self.joker_labels, jokers_in(&self.board), labels);
+        self.board = solution.melds().iter().flat_map(|m| m.tiles().iter().copied()).collect();
+        self.hand = solution.hand().iter().copied().collect();
+        for t in solution.played() { self.played_this_turn.add(*t); }
+        Some(solution)
+    }
+
+    /// A copy of the position with `tile` added to the hand, e.g. to see what could be played after drawing it
+    pub fn with_hand_tile(&self, tile: Tile) -> State {
+        let mut state = self.clone();
+        state.add_to_hand(tile);
+        state
+    }
+
+    /// Whether no hand tile can be played, even by rearranging the board, so the player has to draw
+    pub fn is_stuck(&self) -> bool {
+        solve(self).is_none_or(|s| s.played().is_empty())
+    }
+
+    /// A quick upper bound on how many hand tiles any solve could play, without searching. A tile only counts if
+    /// the board and hand hold the tiles, or jokers, to make a three tile run or group with it, ignoring whether
+    /// those tiles are needed elsewhere
+    pub fn playable_upper_bound(&self) -> usize {
+        let pool: TileMultiset = self.into_iter().collect();
+        let jokers = pool.count(&Tile::Joker);
+        let has = |colour: Colour, number: i32| (1..=Tile::NUMBERS as i32).contains(&number) && pool.count(&Tile::Normal(colour, number as u8)) > 0;
+        let any_real = pool.len() > jokers;
+
+        self.hand.iter().filter(|t| match t {
+            Tile::Joker => any_real,
+            Tile::Normal(colour, number) => {
+                let n = *number as i32;
+                let run = (n - 2..=n).any(|start| {
+                    (start..start + 3).all(|m| (1..=Tile::NUMBERS as i32).contains(&m))
+                        && (start..start + 3).filter(|m| *m != n && !has(*colour, *m)).count() <= jokers
+                });
+                let others = Colour::ALL.iter().filter(|c| *c != colour && has(**c, n)).count();
+                run || others + jokers >= 2
+            }
+        }).count()
+    }
+
+    /// The hand split into the tiles the best solve plays this turn and the ones it leaves behind
+    pub fn partition_hand(&self) -> (Vec<Tile>, Vec<Tile>) {
+        match solve(self) {
+            Some(solution) => (solution.played().to_vec(), solution.hand().to_vec()),
+            None => (Vec::new(), self.hand.iter().copied().collect())
+        }
+    }
+
+    /// Hand tiles that could be given up without playing any fewer tiles, each listed once. Tiles the best solution
+    /// leaves in hand are safe already; each played tile costs one more solve without it
+    pub fn safe_discards(&self) -> Vec<Tile> {
+        let Some(solution) = solve(self) else { return self.hand.iter().copied().collect::<BTreeSet<_>>().into_iter().collect(); };
+        let mut safe: BTreeSet<Tile> = solution.hand().iter().copied().collect();
+
+        let best = solution.played().len();
+        let mut played = solution.played().to_vec();
+        played.dedup();
+        played.retain(|t| !safe.contains(t));
+        for tile in played {
+            let mut without = self.clone();
+            without.hand.remove(&tile);
+            if solve(&without).is_some_and(|s| s.played().len() == best) { safe.insert(tile); }
+        }
+        safe.into_iter().collect()
+    }
+
+    /// Every group of `n`s the board and hand tiles together could make, jokers included. Groups that only differ
+    /// in which colour a joker stands for are listed once
+    pub fn groups_for(&self, n: u8) -> Vec<Meld> {
+        self.groups_for_with_rules(n, &RuleSet::standard())
+    }
+
+    pub fn groups_for_with_rules(&self, n: u8, rules: &RuleSet) -> Vec<Meld> {
+        let colours: Vec<Tile> = Colour::all().map(|c| Tile::Normal(c, n)).filter(|t| self.into_iter().any(|o| o == t)).collect();
+        let jokers = self.into_iter().filter(|t| t.is_joker()).count();
+        let max_group = rules.max_group_size.min(Colour::ALL.len());
+
+        let mut groups = Vec::new();
+        for mask in 1..(1u32 << colours.len()) {
+            let real: Vec<Tile> = colours.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, t)| *t).collect();
+            if real.len() > max_group { continue; }
+            for j in 0..=jokers.min(max_group - real.len()) {
+                if real.len() + j < rules.min_meld_size { continue; }
+                let mut tiles = real.clone();
+                tiles.extend(std::iter::repeat_n(Tile::Joker, j));
+                groups.push(Meld::new_unchecked(tiles, MeldKind::Group, rules));
+            }
+        }
+        groups
+    }
+
+    /// The longest run of `colour` that the board and hand tiles together could make, using as few jokers as
+    /// possible for that length. `None` if no run of the colour can be made at all
+    pub fn longest_possible_run(&self, colour: Colour) -> Option<Meld> {
+        let held: BTreeSet<u8> = self.into_iter().filter(|t| t.is_colour(colour)).filter_map(|t| t.number()).collect();
+        let jokers = self.into_iter().filter(|t| t.is_joker()).count();
+
+        let mut best: Option<(usize, usize, u8, u8)> = None;
+        for lo in 1..=Meld::MAX_NUMBER {
+            for hi in lo..=Meld::MAX_NUMBER {
+                let real = held.range(lo..=hi).count();
+                let len = (hi - lo + 1) as usize;
+                if real == 0 || len - real > jokers || len < Meld::MIN_SIZE { continue; }
+                // Longest first, then fewest jokers
+                if best.is_none_or(|(l, j, _, _)| len > l || (len == l && len - real < j)) {
+                    best = Some((len, len - real, lo, hi));
+                }
+            }
+        }
+
+        let (_, _, lo, hi) = best?;
+        let tiles = (lo..=hi).map(|n| if held.contains(&n) { Tile::Normal(colour, n) } else { Tile::Joker }).collect();
+        Some(Meld::new_unchecked(tiles, MeldKind::Run, &RuleSet::standard()))
+    }
+
+    /// The fewest tiles that, added to the board, would let all of it be arranged into melds - e.g. what was missed
+    /// when copying a board in. Tiles a joker could stand for are given as that tile, unless the deck has no copy
+    /// left. `None` if the board holds more of a tile than the deck does
+    pub fn tiles_needed_for_valid_board(&self) -> Option<Vec<Tile>> {
+        let rules = RuleSet::standard();
+        let deck = rules.deck();
+        let mut on_board: TileMultiset = self.board.iter().collect();
+        if on_board.iter().any(|(t, n)| !deck.contains_at_least(&t, n)) { return None; }
+
+        let solution = self.solve_with_fewest_jokers()?;
+
+        let mut needed = Vec::with_capacity(solution.played().len());
+        let joker_values = solution.melds().iter().flat_map(|m| m.resolve()).filter(|t| t.tile().is_joker());
+        for joker in joker_values.take(solution.played().len()) {
+            let tile = Tile::Normal(joker.colour(), joker.number());
+            if deck.contains_at_least(&tile, on_board.count(&tile) + 1) {
+                on_board.add(tile);
+                needed.push(tile);
+            }
+            else { needed.push(Tile::Joker); }
+        }
+        needed.sort();
+        Some(needed)
+    }
+
+    /// The fewest jokers that, added to the board, would let all of it be arranged into melds. `None` if no number
+    /// would, which only happens when the board is all jokers
+    pub fn min_jokers_to_validate(&self) -> Option<usize> {
+        self.solve_with_fewest_jokers().map(|s| s.played().len())
+    }
+
+    /// Solves the board with a hand of jokers, playing as few of them as possible
+    fn solve_with_fewest_jokers(&self) -> Option<Solution> {
+        // Any leftover tile can be finished with two jokers, so that many always suffice
+        let (_, orphans) = self.max_solvable_board_subset();
+        let spare = vec![Tile::Joker; orphans.len() * 2];
+        // The jokers are made up, so the deck is just what's being solved
+        let universe = self.board.iter().chain(&spare).collect();
+        let config = SolverConfig {
+            objectives: vec![Objective::KeepPreferred],
+            prefer_keep: spare.clone(),
+            rules: RuleSet::with_universe(universe),
+            ..SolverConfig::default()
+        };
+        solve_with_config(&State::from_parts(self.board.clone(), spare.into_iter().collect()), &config)
+    }
+
+    /// Up to `max` distinct ways the board tiles can be arranged into valid melds
+    pub fn all_board_partitions(&self, max: usize) -> Vec<Vec<Meld>> {
+        self.all_board_partitions_with_rules(max, &RuleSet::standard())
+    }
+
+    pub fn all_board_partitions_with_rules(&self, max: usize, rules: &RuleSet) -> Vec<Vec<Meld>> {
+        solver::board_partitions(self, max, rules)
+    }
+
+    /// Pairs of tiles from the board and hand that one more tile would make into a meld, with each tile that would
+    /// do it. Jokers are left out on both sides, as a joker completes any of them
+    pub fn near_misses(&self) -> Vec<(Vec<Tile>, Tile)> {
+        let mut tiles: Vec<Tile> = self.into_iter().filter(|t| !t.is_joker()).copied().collect();
+        tiles.sort();
+        tiles.dedup();
+
+        let rules = RuleSet::standard();
+        let mut near_misses = Vec::new();
+        for (i, a) in tiles.iter().enumerate() {
+            for b in &tiles[i + 1..] {
+                let pair = vec![*a, *b];
+                for needed in Meld::completions(&pair, &rules).into_iter().filter(|t| !t.is_joker()) {
+                    near_misses.push((pair.clone(), needed));
+                }
+            }
+        }
+        near_misses
+    }
+
+    /// Every run or group on the board that `tile` could be added to, and every new three tile meld it could start
+    /// with tiles from the hand
+    pub fn placement_options(&self, tile: &Tile) -> Vec<Placement> {
+        placement::placement_options(self, *tile)
+    }
+
+    /// Each joker on the board, numbered in the order of the solver's arrangement of the board, with the tiles from
+    /// the hand or still unseen that could take its place and free it
+    pub fn retrievable_jokers(&self, rules: &RuleSet) -> Vec<(usize, Vec<Tile>)> {
+        let (melds, _) = self.max_solvable_board_subset();
+        let mut available: Vec<Tile> = self.hand.iter().copied().chain(self.unseen(rules)).collect();
+        available.sort();
+        available.dedup();
+
+        melds.iter()
+            .flat_map(|m| m.resolve())
+            .filter(|r| r.tile().is_joker())
+            .enumerate()
+            .map(|(i, r)| {
+                let replacements = r.joker_alternatives().into_iter()
+                    .map(|(c, n)| Tile::Normal(c, n))
+                    .filter(|t| available.binary_search(t).is_ok())
+                    .collect();
+                (i, replacements)
+            })
+            .collect()
+    }
+
+    /// Tiles of `rules.deck()` that are on neither the board nor the hand
+    pub fn unseen(&self, rules: &RuleSet) -> Vec<Tile> {
+        let seen: TileMultiset = self.into_iter().collect();
+        rules.deck().iter()
+            .flat_map(|(t, total)| std::iter::repeat_n(t, total.saturating_sub(seen.count(&t))))
+            .collect()
+    }
+
+    /// How easy `tile` would be to get back: its unseen copies over one more than the number of pairs in the hand it
+    /// would finish into a meld. Low means it's nearly gone or much needed, so risky to discard
+    pub fn tile_scarcity(&self, tile: &Tile, rules: &RuleSet) -> f64 {
+        let unseen = self.unseen(rules).iter().filter(|t| *t == tile).count();
+
+        let mut others: Vec<Tile> = self.hand.iter().copied().collect();
+        if let Some(i) = others.iter().position(|t| t == tile) { others.remove(i); }
+        others.dedup();
+        let mut demand = 0;
+        for (i, a) in others.iter().enumerate() {
+            for b in &others[i + 1..] {
+                if Meld::completions(&[*a, *b], rules).contains(tile) { demand += 1; }
+            }
+        }
+        unseen as f64 / (demand + 1) as f64
+    }
+
+    /// Draws random unseen tiles one at a time until something can be played, returning how many it took - `0` if
+    /// something already can. `None` if nothing can be played after `max` draws, or once the tiles run out
+    pub fn draws_until_playable(&self, rules: &RuleSet, seed: u64, max: usize) -> Option<usize> {
+        simulate::draws_until_playable(self, rules, seed, max)
+    }
+
+    /// Each distinct unseen tile with how many more tiles could be played after drawing it, the most useful first.
+    /// One solve per tile, spread over threads with the `parallel` feature, each stopping after `max_nodes` if given
+    pub fn rank_draws(&self, rules: &RuleSet, max_nodes: Option<u64>) -> Vec<(Tile, u32)> {
+        simulate::rank_draws(self, rules, max_nodes)
+    }
+
+    /// The unseen tile that would let the most extra tiles be played if drawn. `None` if no tiles are unseen
+    pub fn best_draw(&self, rules: &RuleSet) -> Option<(Tile, u32)> {
+        self.rank_draws(rules, None).first().copied()
+    }
+
+    /// Draws `n` random unseen tiles `trials` times over, solving each time to see how many more tiles could be
+    /// played. Trials run in parallel with the `parallel` feature, but the result only depends on `seed`
+    pub fn simulate_draws(&self, n: usize, trials: usize, rules: &RuleSet, seed: u64) -> DrawStats {
+        simulate::simulate_draws(self, n, trials, rules, seed)
+    }
+
+    /// A version byte, the board and hand lengths as little-endian `u16`s, one `Tile::ordinal` per board and hand
+    /// tile, then for each board and hand joker `0` if it's unlabelled or `1` and its label as a little-endian `u16`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(5 + self.board.len() + self.hand.len());
+        bytes.push(State::BYTES_VERSION);
+        bytes.extend_from_slice(&(self.board.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.hand.len() as u16).to_le_bytes());
+        bytes.extend(self.board.iter().chain(self.hand.iter()).map(|t| t.ordinal()));
+
+        let labels = |list: &[Option<u16>], jokers| list.iter().copied().chain(std::iter::repeat(None)).take(jokers).collect::<Vec<_>>();
+        for label in labels(&self.joker_labels, jokers_in(&self.board)).into_iter().chain(labels(&self.hand_joker_labels, jokers_in(&self.hand))) {
+            match label {
+                Some(label) => { bytes.push(1); bytes.extend_from_slice(&label.to_le_bytes()); },
+                None => bytes.push(0)
+            }
+        }
+        bytes
+    }
+
+    /// Also reads version 1, which had no hand length or labels
+    pub fn from_bytes(bytes: &[u8]) -> Result<State, &'static str> {
+        if bytes.len() < 3 { return Err("Too short"); }
+        let (board_len, hand_len, tiles) = match bytes[0] {
+            1 => (u16::from_le_bytes([bytes[1], bytes[2]]) as usize, None, &bytes[3..]),
+            State::BYTES_VERSION if bytes.len() >= 5 => (
+                u16::from_le_bytes([bytes[1], bytes[2]]) as usize,
+                Some(u16::from_le_bytes([bytes[3], bytes[4]]) as usize),
+                &bytes[5..]
+            ),
+            State::BYTES_VERSION => return Err("Too short"),
+            _ => return Err("Unsupported version")
+        };
+        if tiles.len() < board_len { return Err("Board truncated"); }
+        let hand_len = hand_len.unwrap_or(tiles.len() - board_len);
+        if tiles.len() < board_len + hand_len { return Err("Hand truncated"); }
+
+        let (tiles, mut rest) = tiles.split_at(board_len + hand_len);
+        let tiles = tiles.iter().map(|b| Tile::from_ordinal(*b).ok_or("Invalid tile")).collect::<Result<Vec<Tile>, _>>()?;
+        let mut read_label = |t: &Tile| -> Result<(Tile, Option<u16>), &'static str> {
+            if !t.is_joker() || rest.is_empty() { return Ok((*t, None)); }
+            match rest {
+                [0, more @ ..] => { rest = more; Ok((*t, None)) },
+                [1, a, b, more @ ..] => { rest = more; Ok((*t, Some(u16::from_le_bytes([*a, *b])))) },
+                _ => Err("Invalid joker label")
+            }
+        };
+        // Jokers sort last, so reading the labels in tile order matches them to the jokers in order
+        let board = tiles[..board_len].iter().map(&mut read_label).collect::<Result<Vec<_>, _>>()?;
+        let hand = tiles[board_len..].iter().map(&mut read_label).collect::<Result<Vec<_>, _>>()?;
+        let mut state = State::new();
+        state.add_labelled(true, board);
+        state.add_labelled(false, hand);
+        Ok(state)
+    }
+
+    pub fn format(&mut self) -> String {
+        let (board, hand) = self.format_parts();
+        format!("Board:\n{board}Hand:\n{hand}")
+    }
+
+    /// The board and hand lists from `format`, without their headings
+    pub fn format_parts(&mut self) -> (String, String) {
+        let board = Tile::format_list_labelled(self.board.make_contiguous(), &DEFAULT_CONFIG, &self.joker_labels);
+        (board, Tile::format_list_labelled(self.hand.make_contiguous(), &DEFAULT_CONFIG, &self.hand_joker_labels))
+    }
+
+    /// The colours of the board's real tiles
+    pub fn board_colours(&self) -> BTreeSet<Colour> {
+        self.board.iter().filter_map(|t| t.colour()).collect()
+    }
+
+    pub fn hand_colours(&self) -> BTreeSet<Colour> {
+        self.hand.iter().filter_map(|t| t.colour()).collect()
+    }
+
+    /// Panics if the board or hand is out of order, which every change to them should rule out. Debug builds check
+    /// this before each solve
+    pub fn assert_sorted(&self) {
+        assert!(self.board.is_sorted(), "board is out of order: {:?}", self.board);
+        assert!(self.hand.is_sorted(), "hand is out of order: {:?}", self.hand);
+    }
+
+    pub fn board(&self) -> &Board { &self.board }
+    pub fn hand(&self) -> &Hand { &self.hand }
+    pub fn board_mut(&mut self) -> &mut Board { &mut self.board }
+    pub fn hand_mut(&mut self) -> &mut Hand { &mut self.hand }
+}
+
+impl Default for State {
+    fn default() -> Self { State::new() }
+}
+
+/// Jokers sort last, so they're counted from the back
+fn jokers_in(tiles: &VecDeque<Tile>) -> usize {
+    tiles.iter().rev().take_while(|t| t.is_joker()).count()
+}
+
+/// Adds the labels of jokers added after the first `before`. Labels past those jokers belonged to jokers since
+/// taken away, so are dropped
+fn push_labels(labels: &mut Vec<Option<u16>>, before: usize, new: Vec<Option<u16>>) {
+    labels.truncate(before);
+    if labels.is_empty() && new.iter().all(Option::is_none) { return; }
+    labels.resize(before, None);
+    labels.extend(new);
+}
+
+/// Takes the labels of the first `n` of `jokers` jokers, e.g. as they're played
+fn take_labels(labels: &mut Vec<Option<u16>>, jokers: usize, n: usize) -> Vec<Option<u16>> {
+    labels.truncate(jokers);
+    let mut taken: Vec<Option<u16>> = labels.drain(..n.min(labels.len())).collect();
+    taken.resize(n, None);
+    taken
+}
+
+/// Board tiles followed by hand tiles
+impl IntoIterator for State {
+    type Item = Tile;
+    type IntoIter = Chain<vec_deque::IntoIter<Tile>, vec_deque::IntoIter<Tile>>;
+
+    fn into_iter(self) -> Self::IntoIter { self.board.into_iter().chain(self.hand) }
+}
+
+impl<'a> IntoIterator for &'a State {
+    type Item = &'a Tile;
+    type IntoIter = Chain<vec_deque::Iter<'a, Tile>, vec_deque::Iter<'a, Tile>>;
+
+    fn into_iter(self) -> Self::IntoIter { self.board.iter().chain(self.hand.iter()) }
+}
+
+/// As for `&State`. The tiles are only lent out for reading, so the board and hand stay sorted
+impl<'a> IntoIterator for &'a mut State {
+    type Item = &'a Tile;
+    type IntoIter = Chain<vec_deque::Iter<'a, Tile>, vec_deque::Iter<'a, Tile>>;
+
+    fn into_iter(self) -> Self::IntoIter { self.board.iter().chain(self.hand.iter()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::position;
+
+    #[test]
+    fn jokers_sort_last() {
+        let last = |tiles: &VecDeque<Tile>| tiles.iter().skip_while(|t| !t.is_joker()).all(|t| t.is_joker());
+        assert!(Colour::all().all(|c| Tile::Normal(c, u8::MAX) < Tile::Joker));
+
+        let mut state = position("j r5 j x13", "j b1 y13");
+        state.add_to_board(Tile::Normal(Colour::Red, 1));
+        state.add_to_hand(Tile::Joker);
+        state.add_to_hand(Tile::Normal(Colour::Black, 2));
+        assert!(last(state.board()));
+        assert!(last(state.hand()));
+
+        let mut board: Board = [Tile::Joker, Tile::Normal(Colour::Blue, 3), Tile::Joker].into_iter().collect();
+        board.insert(Tile::Normal(Colour::Black, 13));
+        board.extend([Tile::Joker, Tile::Normal(Colour::Red, 7)]);
+        assert_eq!(board.back(), Some(&Tile::Joker));
+        assert!(last(&board));
+    }
+
+    #[test]
+    fn configured_joker_symbol() {
+        let config = DisplayConfig { joker_symbol: "*".to_string(), ..DisplayConfig::default() };
+        let mut state = State::new();
+        state.add_board_line_with("r1 * *2", &config).unwrap();
+        state.add_hand_line_with("*", &config).unwrap();
+        assert_eq!(state.board().iter().copied().collect::<Vec<_>>(), vec![Tile::Normal(Colour::Red, 1), Tile::Joker, Tile::Joker]);
+        assert_eq!(state.joker_labels(), [None, Some(2)]);
+        assert_eq!(state.hand().iter().copied().collect::<Vec<_>>(), vec![Tile::Joker]);
+        assert!(state.add_board_line_with("j", &config).is_err());
+    }
+
+    #[test]
+    fn commit_needs_begin() {
+        let mut state = position("r1 r2 r3", "r4");
+        state.play_from_hand(Tile::Normal(Colour::Red, 4));
+        assert!(!state.commit());
+        assert_eq!(state.played_this_turn().len(), 1);
+
+        state.begin();
+        assert!(state.commit());
+        assert!(state.played_this_turn().is_empty());
+    }
+
+    #[test]
+    fn number_first() {
+        assert_eq!(Tile::from_str_number_first("12b"), Ok(Tile::Normal(Colour::Blue, 12)));
+        assert_eq!(Tile::from_str_number_first("Joker"), Ok(Tile::Joker));
+        assert_eq!(Tile::from_str_number_first("1j"), Err("Joker with a number"));
+        assert_eq!(Tile::from_str_number_first("12"), Err("Invalid colour"));
+    }
+
+    #[test]
+    fn iterates_board_then_hand() {
+        let mut state = position("r2 r1", "b1 j");
+        let expected = vec![Tile::Normal(Colour::Red, 1), Tile::Normal(Colour::Red, 2), Tile::Normal(Colour::Blue, 1), Tile::Joker];
+        assert_eq!((&state).into_iter().copied().collect::<Vec<_>>(), expected);
+        let mut borrowed = Vec::new();
+        for t in &mut state { borrowed.push(*t); }
+        assert_eq!(borrowed, expected);
+        assert_eq!(state.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        for (board, hand) in [("", ""), ("r1 r2 r3 j", "b13 j j"), ("y5 x5 j2", "j7 j r1"), ("", "y9")] {
+            let state = position(board, hand);
+            let read = State::from_bytes(&state.to_bytes()).unwrap();
+            assert_eq!(read.board(), state.board());
+            assert_eq!(read.hand(), state.hand());
+            assert_eq!(read.joker_labels(), state.joker_labels());
+            assert_eq!(read.hand_joker_labels(), state.hand_joker_labels());
+        }
+    }
+
+    #[test]
+    fn bytes_version_one() {
+        let read = State::from_bytes(&[1, 2, 0, Tile::Joker.ordinal(), Tile::Normal(Colour::Red, 1).ordinal(), Tile::Joker.ordinal()]).unwrap();
+        assert_eq!(read.board().iter().copied().collect::<Vec<_>>(), vec![Tile::Normal(Colour::Red, 1), Tile::Joker]);
+        assert_eq!(read.hand().iter().copied().collect::<Vec<_>>(), vec![Tile::Joker]);
+    }
+
+    #[test]
+    fn bad_bytes() {
+        assert!(State::from_bytes(&[]).is_err());
+        assert!(State::from_bytes(&[0, 0, 0]).is_err());
+        assert!(State::from_bytes(&[State::BYTES_VERSION, 2, 0, 0, 0, 1]).is_err());
+        assert!(State::from_bytes(&[State::BYTES_VERSION, 1, 0, 0, 0, 255]).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use std::fmt;
+use crate::{Colour, RuleSet, Tile};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum MeldKind {
+    Run,
+    Group
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MeldError {
+    TooFewTiles,
+    TooManyTiles,
+    NoRealTiles,
+    MixedColours,
+    NotIncreasing,
+    Gap,
+    OutOfRange,
+    MixedNumbers,
+    RepeatedColour,
+    /// A joker in the run already stands for that tile, so it could only go in by swapping the joker out
+    SlotHeldByJoker,
+    Neither(Box<MeldError>, Box<MeldError>)
+}
+
+impl fmt::Display for MeldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeldError::TooFewTiles => write!(f, "Too few tiles"),
+            MeldError::TooManyTiles => write!(f, "Too many tiles"),
+            MeldError::NoRealTiles => write!(f, "No real tiles"),
+            MeldError::MixedColours => write!(f, "Run must be a single colour"),
+            MeldError::NotIncreasing => write!(f, "Run numbers must strictly increase"),
+            MeldError::Gap => write!(f, "Run has a gap"),
+            MeldError::OutOfRange => write!(f, "Run out of range"),
+            MeldError::MixedNumbers => write!(f, "Group must be a single number"),
+            MeldError::RepeatedColour => write!(f, "Group can't repeat a colour"),
+            MeldError::SlotHeldByJoker => write!(f, "A joker already stands for that tile"),
+            MeldError::Neither(run, group) => write!(f, "Not a run ({run}) or a group ({group})")
+        }
+    }
+}
+
+/// A valid run or group. Tiles are kept in display order so jokers in a run sit in the position they stand in for
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Meld {
+    tiles: Vec<Tile>,
+    kind: MeldKind,
+    /// Bit `i` is set if `tiles[i]` is a wild colour tile standing in for a joker
+    wild: u16
+}
+
+impl Meld {
+    pub const MIN_SIZE: usize = 3;
+    pub const MAX_GROUP_SIZE: usize = 4;
+    pub const MAX_NUMBER: u8 = 13;
+
+    /// Builds a run if the tiles form one, otherwise a group
+    pub fn new(tiles: Vec<Tile>) -> Result<Meld, MeldError> {
+        Meld::new_with_rules(tiles, &RuleSet::standard())
+    }
+
+    pub fn new_with_rules(tiles: Vec<Tile>, rules: &RuleSet) -> Result<Meld, MeldError> {
+        let run_error = match Meld::run_with_rules(tiles.clone(), rules) {
+            Ok(m) => return Ok(m),
+            Err(e) => e
+        };
+        let group_error = match Meld::group_with_rules(tiles, rules) {
+            Ok(m) => return Ok(m),
+            Err(e) => e
+        };
+
+        if run_error == group_error { Err(run_error) }
+        else { Err(MeldError::Neither(Box::new(run_error), Box::new(group_error))) }
+    }
+
+    pub fn run(tiles: Vec<Tile>) -> Result<Meld, MeldError> {
+        Meld::run_with_rules(tiles, &RuleSet::standard())
+    }
+
+    pub fn run_with_rules(tiles: Vec<Tile>, rules: &RuleSet) -> Result<Meld, MeldError> {
+        Meld::allowing_wilds(tiles, rules, Meld::plain_run)
+    }
+
+    fn plain_run(tiles: Vec<Tile>, rules: &RuleSet) -> Result<Meld, MeldError> {
+        if tiles.len() < rules.min_meld_size { return Err(MeldError::TooFewTiles); }
+        if tiles.len() > rules.max_number as usize { return Err(MeldError::TooManyTiles); }
+        if tiles.iter().all(|t| t.is_joker()) { return Err(MeldError::NoRealTiles); }
+
+        if Meld::is_ordered_run(&tiles, rules) { return Ok(Meld { tiles, kind: MeldKind::Run, wild: 0 }); }
+
+        let jokers = tiles.iter().filter(|t| t.is_joker()).count();
+        let mut real: Vec<Tile> = tiles.into_iter().filter(|t| !t.is_joker()).collect();
+        real.sort();
+
+        let colour = real[0].colour();
+        if real.iter().any(|t| t.colour() != colour) { return Err(MeldError::MixedColours); }
+
+        let mut ordered = Vec::with_capacity(real.len() + jokers);
+        let mut spare = jokers;
+        for (i, t) in real.iter().enumerate() {
+            if i > 0 {
+                let gap = t.number().unwrap() - real[i - 1].number().unwrap();
+                if gap == 0 { return Err(MeldError::NotIncreasing); }
+                if (gap - 1) as usize > spare { return Err(MeldError::Gap); }
+                for _ in 1..gap { ordered.push(Tile::Joker); }
+                spare -= (gap - 1) as usize;
+            }
+            ordered.push(*t);
+        }
+
+        // Spare jokers extend the top of the run first, then the bottom
+        let top = real.last().unwrap().number().unwrap();
+        let after = spare.min(rules.max_number.saturating_sub(top) as usize);
+        for _ in 0..after { ordered.push(Tile::Joker); }
+        for _ in after..spare { ordered.insert(0, Tile::Joker); }
+
+        if !Meld::is_ordered_run(&ordered, rules) { return Err(MeldError::OutOfRange); }
+        Ok(Meld { tiles: ordered, kind: MeldKind::Run, wild: 0 })
+    }
+
+    pub fn group(tiles: Vec<Tile>) -> Result<Meld, MeldError> {
+        Meld::group_with_rules(tiles, &RuleSet::standard())
+    }
+
+    pub fn group_with_rules(tiles: Vec<Tile>, rules: &RuleSet) -> Result<Meld, MeldError> {
+        Meld::allowing_wilds(tiles, rules, Meld::plain_group)
+    }
+
+    fn plain_group(tiles: Vec<Tile>, rules: &RuleSet) -> Result<Meld, MeldError> {
+        if tiles.len() < rules.min_meld_size { return Err(MeldError::TooFewTiles); }
+        // Each tile needs its own colour, so there's no room for more than that whatever the rules say
+        if tiles.len() > rules.max_group_size.min(Colour::ALL.len()) { return Err(MeldError::TooManyTiles); }
+        if tiles.iter().all(|t| t.is_joker()) { return Err(MeldError::NoRealTiles); }
+
+        let mut number = None;
+        let mut colours = Vec::with_capacity(tiles.len());
+        for t in tiles.iter().filter(|t| !t.is_joker()) {
+            if number.is_some() && number != t.number() { return Err(MeldError::MixedNumbers); }
+            if colours.contains(&t.colour()) { return Err(MeldError::RepeatedColour); }
+            number = t.number();
+            colours.push(t.colour());
+        }
+
+        Ok(Meld { tiles, kind: MeldKind::Group, wild: 0 })
+    }
+
+    /// Tries the tiles as they are, then with ever more of the wild colour tiles standing in for jokers
+    fn allowing_wilds(tiles: Vec<Tile>, rules: &RuleSet, build: fn(Vec<Tile>, &RuleSet) -> Result<Meld, MeldError>) -> Result<Meld, MeldError> {
+        let wild: Vec<usize> = (0..tiles.len()).filter(|&i| rules.is_wild(&tiles[i])).collect();
+        if wild.is_empty() { return build(tiles, rules); }
+
+        let mut masks: Vec<u32> = (0..1u32 << wild.len()).collect();
+        masks.sort_by_key(|m| m.count_ones());
+        let mut first_error = None;
+        for mask in masks {
+            let mut stand_ins = Vec::new();
+            let mut as_jokers = tiles.clone();
+            for (bit, &i) in wild.iter().enumerate() {
+                if mask & (1 << bit) != 0 { as_jokers[i] = Tile::Joker; }
+            }
+            // Real jokers and the wild tiles acting as jokers fill the joker positions in the order they were given
+            for (i, t) in tiles.iter().enumerate() {
+                if as_jokers[i].is_joker() { stand_ins.push(*t); }
+            }
+            match build(as_jokers, rules) {
+                Ok(m) => return Ok(m.fill_jokers(&mut stand_ins.into_iter())),
+                Err(e) => { first_error.get_or_insert(e); }
+            }
+        }
+        Err(first_error.unwrap())
+    }
+
+    /// Puts tiles from `stand_ins` into the joker positions in order, marking any that aren't jokers as wild
+    pub(crate) fn fill_jokers(mut self, stand_ins: &mut impl Iterator<Item=Tile>) -> Meld {
+        for (i, t) in self.tiles.iter_mut().enumerate() {
+            if !t.is_joker() { continue; }
+            let Some(stand_in) = stand_ins.next() else { break; };
+            if !stand_in.is_joker() { self.wild |= 1 << i; }
+            *t = stand_in;
+        }
+        self
+    }
+
+    /// Whether the tile at `i` is a joker or stands in for one
+    fn acts_as_joker(&self, i: usize) -> bool { self.tiles[i].is_joker() || self.wild & (1 << i) != 0 }
+
+    /// The tiles with any wild stand-ins shown as the jokers they act as
+    pub(crate) fn joker_view(&self) -> Vec<Tile> {
+        (0..self.tiles.len()).map(|i| if self.acts_as_joker(i) { Tile::Joker } else { self.tiles[i] }).collect()
+    }
+
+    /// Tiles that would turn a partial set into a valid meld, e.g. `r4`, `r7` or a joker for `r5 r6`
+    pub fn completions(tiles: &[Tile], rules: &RuleSet) -> Vec<Tile> {
+        (0..Tile::KINDS as u8)
+            .map(|o| Tile::from_ordinal(o).unwrap())
+            .filter(|t| t.number().is_none_or(|n| n <= rules.max_number))
+            .filter(|t| rules.universe.as_ref().is_none_or(|u| u.count(t) > 0))
+            .filter(|t| {
+                let mut with = tiles.to_vec();
+                with.push(*t);
+                Meld::new_with_rules(with, rules).is_ok()
+            })
+            .collect()
+    }
+
+    /// Checks whether the tiles, in the order given, are consecutive with jokers filling their own positions
+    fn is_ordered_run(tiles: &[Tile], rules: &RuleSet) -> bool {
+        let Some((first, t)) = tiles.iter().enumerate().find(|(_, t)| !t.is_joker()) else { return false; };
+        let start = t.number().unwrap() as i32 - first as i32;
+        if start < 1 || start + tiles.len() as i32 - 1 > rules.max_number as i32 { return false; }
+
+        tiles.iter().enumerate().all(|(i, o)| {
+            o.is_joker() || (o.colour() == t.colour() && o.number().unwrap() as i32 == start + i as i32)
+        })
+    }
+
+    /// For tiles already known to form a meld of this kind under `rules`. Debug builds still check, so a run with a
+    /// gap that no joker fills can't slip out of the solver
+    pub(crate) fn new_unchecked(tiles: Vec<Tile>, kind: MeldKind, rules: &RuleSet) -> Meld {
+        debug_assert!(match kind {
+            MeldKind::Run => Meld::is_ordered_run(&tiles, rules) && tiles.len() >= rules.min_meld_size,
+            MeldKind::Group => Meld::plain_group(tiles.clone(), rules).is_ok()
+        }, "invalid {kind:?}: {tiles:?}");
+        Meld { tiles, kind, wild: 0 }
+    }
+
+    /// The colour and the lowest and highest numbers covered by a run, counting jokers
+    pub fn run_range(&self) -> Option<(Colour, u8, u8)> {
+        if self.kind != MeldKind::Run { return None; }
+
+        let (i, t) = self.tiles.iter().enumerate().find(|(i, _)| !self.acts_as_joker(*i))?;
+        let start = t.number().unwrap() - i as u8;
+        Some((t.colour().unwrap(), start, start + self.tiles.len() as u8 - 1))
+    }
+
+    /// The value each tile stands for, jokers included, along with what else each joker could be. A joker between a
+    /// run's real tiles can only be the number it has and a group's jokers can be any missing colour. Jokers past a
+    /// run's real tiles can slide together, so each lists every number either end could take, e.g. both jokers of
+    /// `r5 r6 j j` list r3, r4, r7 and r8
+    pub fn resolve(&self) -> Vec<ResolvedTile> {
+        self.resolve_with_rules(&RuleSet::standard())
+    }
+
+    pub fn resolve_with_rules(&self, rules: &RuleSet) -> Vec<ResolvedTile> {
+        match self.kind {
+            MeldKind::Run => {
+                let (colour, start, end) = self.run_range().unwrap();
+                let real: Vec<u8> = self.joker_view().iter().filter_map(|t| t.number()).collect();
+                let (lo, hi) = (real[0], *real.last().unwrap());
+                // Jokers beyond the real tiles can sit at either end, as long as the run still fits
+                let len = self.tiles.len() as u8;
+                let lowest = hi.saturating_sub(len - 1).max(1);
+                let highest = (lo + len - 1).min(rules.max_number);
+                let ends: Vec<(Colour, u8)> = (lowest..lo).chain(hi + 1..=highest).map(|n| (colour, n)).collect();
+
+                self.tiles.iter().enumerate().zip(start..=end).map(|((i, t), n)| ResolvedTile {
+                    tile: *t,
+                    colour,
+                    number: n,
+                    alternatives: if !self.acts_as_joker(i) { Vec::new() }
+                        else if n < lo || n > hi { ends.clone() }
+                        else { vec![(colour, n)] }
+                }).collect()
+            },
+            MeldKind::Group => {
+                let view = self.joker_view();
+                let number = view.iter().find_map(|t| t.number()).unwrap();
+                let missing: Vec<(Colour, u8)> = Colour::all()
+                    .filter(|c| !view.iter().any(|t| t.colour() == Some(*c)))
+                    .map(|c| (c, number))
+                    .collect();
+
+                let mut next_missing = missing.iter();
+                self.tiles.iter().zip(view).map(|(t, v)| {
+                    let (colour, number) = match v {
+                        Tile::Normal(c, n) => (c, n),
+                        Tile::Joker => *next_missing.next().unwrap()
+                    };
+                    ResolvedTile {
+                        tile: *t,
+                        colour,
+                        number,
+                        alternatives: if v.is_joker() { missing.clone() } else { Vec::new() }
+                    }
+                }).collect()
+            }
+        }
+    }
+
+    /// Whether `tile` can be added to the meld, where it would go and the meld it would make. Jokers in the meld
+    /// keep the places they have, so a tile a joker stands for is `Rejected(SlotHeldByJoker)`
+    pub fn accepts(&self, tile: &Tile) -> AcceptResult {
+        if self.kind == MeldKind::Group {
+            let mut tiles = self.tiles.clone();
+            tiles.push(*tile);
+            return match Meld::group(tiles) {
+                Ok(m) => AcceptResult::Group(m),
+                Err(e) => AcceptResult::Rejected(e)
+            };
+        }
+
+        let held = self.resolve().iter().enumerate()
+            .any(|(i, r)| self.acts_as_joker(i) && Tile::Normal(r.colour, r.number) == *tile);
+        if held { return AcceptResult::Rejected(MeldError::SlotHeldByJoker); }
+
+        let mut back = self.tiles.clone();
+        back.push(*tile);
+        let mut front = vec![*tile];
+        front.extend(&self.tiles);
+
+        let error = match Meld::run(back.clone()) {
+            Ok(m) if m.tiles == back => return AcceptResult::RunBack(m),
+            Ok(_) => MeldError::NotIncreasing,
+            Err(e) => e
+        };
+        match Meld::run(front.clone()) {
+            Ok(m) if m.tiles == front => AcceptResult::RunFront(m),
+            _ => AcceptResult::Rejected(error)
+        }
+    }
+
+    /// The sum of the numbers in the meld, with jokers worth the number they stand for
+    pub fn points(&self) -> u32 {
+        self.resolve().iter().map(|t| t.number as u32).sum()
+    }
+
+    pub fn tiles(&self) -> &[Tile] { &self.tiles }
+    pub fn kind(&self) -> MeldKind { self.kind }
+}
+
+/// Whether a set of tiles can only be laid out as one meld, from `meld_ambiguity`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MeldAmbiguity {
+    Unique(Meld),
+    Multiple(Vec<Meld>),
+    Invalid
+}
+
+/// Every meld the tiles could make together: as a group, or as a run with any spare jokers split between its ends,
+/// e.g. `r4 r5 r6 r7 j` can run from 3 or up to 8
+pub fn meld_ambiguity(tiles: &[Tile]) -> MeldAmbiguity {
+    let mut melds = Vec::new();
+    let standard = RuleSet::standard();
+    if let Ok(run) = Meld::run(tiles.to_vec()) {
+        let lead = run.tiles.iter().take_while(|t| t.is_joker()).count();
+        let trail = run.tiles.iter().rev().take_while(|t| t.is_joker()).count();
+        let middle = &run.tiles[lead..run.tiles.len() - trail];
+        for before in 0..=lead + trail {
+            let mut layout = vec![Tile::Joker; before];
+            layout.extend(middle);
+            layout.extend(std::iter::repeat_n(Tile::Joker, lead + trail - before));
+            if Meld::is_ordered_run(&layout, &standard) { melds.push(Meld::new_unchecked(layout, MeldKind::Run, &standard)); }
+        }
+    }
+    if let Ok(group) = Meld::group(tiles.to_vec()) { melds.push(group); }
+
+    match melds.len() {
+        0 => MeldAmbiguity::Invalid,
+        1 => MeldAmbiguity::Unique(melds.pop().unwrap()),
+        _ => MeldAmbiguity::Multiple(melds)
+    }
+}
+
+/// Where a tile dropped on a meld would go, from `Meld::accepts`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AcceptResult {
+    RunFront(Meld),
+    RunBack(Meld),
+    Group(Meld),
+    Rejected(MeldError)
+}
+
+/// A tile in a meld along with the colour and number it stands for
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ResolvedTile {
+    pub(crate) tile: Tile,
+    pub(crate) colour: Colour,
+    pub(crate) number: u8,
+    pub(crate) alternatives: Vec<(Colour, u8)>
+}
+
+impl ResolvedTile {
+    pub fn tile(&self) -> Tile { self.tile }
+    pub fn colour(&self) -> Colour { self.colour }
+    pub fn number(&self) -> u8 { self.number }
+
+    /// The values a joker could stand for, including the one it has now, as laid out by `Meld::resolve`. Empty for
+    /// real tiles
+    pub fn joker_alternatives(&self) -> Vec<(Colour, u8)> { self.alternatives.clone() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, State};
+    use crate::examples::tiles;
+
+    #[test]
+    fn repeated_tile_is_neither_run_nor_group() {
+        let run = Meld::run(tiles("r5 r5 r5")).unwrap_err();
+        let group = Meld::group(tiles("r5 r5 r5")).unwrap_err();
+        assert_eq!(run, MeldError::NotIncreasing);
+        assert_eq!(group, MeldError::RepeatedColour);
+        assert_ne!(run.to_string(), group.to_string());
+        assert_eq!(Meld::new(tiles("r5 r5 r5")), Err(MeldError::Neither(Box::new(run), Box::new(group))));
+    }
+
+    #[test]
+    fn joker_holds_its_number() {
+        let run = Meld::run(tiles("r5 r6 j")).unwrap();
+        assert_eq!(run.accepts(&Tile::from_str("r7").unwrap()), AcceptResult::Rejected(MeldError::SlotHeldByJoker));
+        assert!(matches!(run.accepts(&Tile::from_str("r8").unwrap()), AcceptResult::RunBack(_)));
+        assert!(matches!(run.accepts(&Tile::from_str("r4").unwrap()), AcceptResult::RunFront(_)));
+        assert_eq!(run.accepts(&Tile::from_str("r6").unwrap()), AcceptResult::Rejected(MeldError::NotIncreasing));
+    }
+
+    #[test]
+    fn end_jokers_list_both_ends() {
+        let resolved = Meld::run(tiles("r5 r6 j j")).unwrap().resolve();
+        let ends: Vec<(Colour, u8)> = [3, 4, 7, 8].map(|n| (Colour::Red, n)).into();
+        assert_eq!(resolved[2].joker_alternatives(), ends);
+        assert_eq!(resolved[3].joker_alternatives(), ends);
+        assert!(resolved[0].joker_alternatives().is_empty());
+
+        let middle = Meld::run(tiles("r5 j r7")).unwrap().resolve();
+        assert_eq!(middle[1].joker_alternatives(), vec![(Colour::Red, 6)]);
+    }
+
+    /// Checks every run has consecutive values, jokers included
+    fn consecutive(meld: &Meld) -> bool {
+        meld.kind() == MeldKind::Group || meld.resolve().windows(2).all(|w| w[0].number() + 1 == w[1].number())
+    }
+
+    #[test]
+    fn gaps_need_a_joker() {
+        let run = |numbers: &mut dyn Iterator<Item=u8>| numbers.map(|n| Tile::Normal(Colour::Black, n)).collect::<Vec<_>>();
+        for start in 1..=Meld::MAX_NUMBER {
+            for end in start + 3..=Meld::MAX_NUMBER {
+                for missing in start + 1..end {
+                    let gapped = run(&mut (start..=end).filter(|&n| n != missing));
+                    assert_eq!(Meld::run(gapped.clone()), Err(MeldError::Gap), "{gapped:?}");
+
+                    let mut filled = gapped.clone();
+                    filled.push(Tile::Joker);
+                    let meld = Meld::run(filled).unwrap();
+                    assert!(consecutive(&meld));
+                    assert!(meld.resolve().iter().any(|r| r.tile().is_joker() && r.number() == missing));
+
+                    for other in missing + 2..end {
+                        let mut twice = run(&mut (start..=end).filter(|&n| n != missing && n != other));
+                        twice.push(Tile::Joker);
+                        assert_eq!(Meld::run(twice), Err(MeldError::Gap));
+                    }
+
+                    // The solver may split the tiles, but never into a run with a gap
+                    for board in [gapped.clone(), [gapped, vec![Tile::Joker]].concat()] {
+                        let mut state = State::new();
+                        state.add_many_to_board(board);
+                        let Some(solution) = solve(&state) else { continue; };
+                        assert!(solution.verify(&state).is_ok());
+                        assert!(solution.melds().iter().all(consecutive), "{}", solution.format());
+                    }
+                }
+            }
+        }
+    }
+}
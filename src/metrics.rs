@@ -0,0 +1,63 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use crate::SolveEvent;
+
+/// Counters for solves, fed by `SolverConfig::on_event` through `hook`, and written out in the Prometheus text
+/// format. Safe to share between threads solving at once
+#[derive(Debug, Default)]
+pub struct SolveMetrics {
+    solves: AtomicU64,
+    nodes: AtomicU64,
+    budget_exhausted: AtomicU64,
+    /// Solves taking at most each of `BUCKETS` seconds
+    buckets: [AtomicU64; SolveMetrics::BUCKETS.len()],
+    /// In microseconds, to keep to integer atomics
+    duration_micros: AtomicU64
+}
+
+impl SolveMetrics {
+    /// Upper bounds of the solve duration histogram, in seconds
+    pub const BUCKETS: [f64; 5] = [0.001, 0.01, 0.1, 1.0, 10.0];
+
+    pub fn new() -> Arc<SolveMetrics> { Arc::new(SolveMetrics::default()) }
+
+    /// A callback for `SolverConfig::on_event` that records into these metrics
+    pub fn hook(self: &Arc<Self>) -> Arc<dyn Fn(SolveEvent) + Send + Sync> {
+        let metrics = self.clone();
+        Arc::new(move |event| metrics.record(&event))
+    }
+
+    pub fn record(&self, event: &SolveEvent) {
+        match event {
+            SolveEvent::BudgetExhausted { .. } | SolveEvent::TimedOut { .. } => { self.budget_exhausted.fetch_add(1, Ordering::Relaxed); },
+            SolveEvent::Finished { nodes, elapsed, .. } => {
+                self.solves.fetch_add(1, Ordering::Relaxed);
+                self.nodes.fetch_add(*nodes, Ordering::Relaxed);
+                self.duration_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+                for (bound, count) in SolveMetrics::BUCKETS.iter().zip(&self.buckets) {
+                    if elapsed.as_secs_f64() <= *bound { count.fetch_add(1, Ordering::Relaxed); }
+                }
+            },
+            SolveEvent::Started { .. } | SolveEvent::Improved { .. } => {}
+        }
+    }
+
+    /// The metrics as Prometheus text, e.g. for a `/metrics` endpoint
+    pub fn to_prometheus(&self) -> String {
+        let solves = self.solves.load(Ordering::Relaxed);
+        let mut out = String::new();
+        writeln!(out, "# TYPE rummikub_solves_total counter\nrummikub_solves_total {solves}").unwrap();
+        writeln!(out, "# TYPE rummikub_nodes_total counter\nrummikub_nodes_total {}", self.nodes.load(Ordering::Relaxed)).unwrap();
+        writeln!(out, "# TYPE rummikub_budget_exhausted_total counter\nrummikub_budget_exhausted_total {}", self.budget_exhausted.load(Ordering::Relaxed)).unwrap();
+
+        writeln!(out, "# TYPE rummikub_solve_duration_seconds histogram").unwrap();
+        for (bound, count) in SolveMetrics::BUCKETS.iter().zip(&self.buckets) {
+            writeln!(out, "rummikub_solve_duration_seconds_bucket{{le=\"{bound}\"}} {}", count.load(Ordering::Relaxed)).unwrap();
+        }
+        writeln!(out, "rummikub_solve_duration_seconds_bucket{{le=\"+Inf\"}} {solves}").unwrap();
+        writeln!(out, "rummikub_solve_duration_seconds_sum {}", self.duration_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0).unwrap();
+        writeln!(out, "rummikub_solve_duration_seconds_count {solves}").unwrap();
+        out
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use crate::{Hand, Meld, MeldError, MeldKind, RuleSet, State, Tile, TileMultiset};
+
+/// Why a move was rejected by `validate_move`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MoveError {
+    InvalidMeld { index: usize, error: MeldError },
+    /// A tile from the previous board is missing from the new one
+    MissingBoardTile(Tile),
+    /// A tile was added that the player doesn't hold, or holds fewer copies of
+    NotInHand(Tile),
+    NothingPlayed,
+    /// The board holds more copies of a tile than `RuleSet::deck` has
+    NotInDeck(Tile),
+    /// Before opening, the tiles played must form whole melds of their own
+    OpeningUsesBoard,
+    OpeningTooLow { points: u32, needed: u32 }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::InvalidMeld { index, error } => write!(f, "Meld {index} is invalid: {error}"),
+            MoveError::MissingBoardTile(t) => write!(f, "{} was taken off the board", t.to_string()),
+            MoveError::NotInHand(t) => write!(f, "{} isn't in the player's hand", t.to_string()),
+            MoveError::NothingPlayed => write!(f, "No tiles were played"),
+            MoveError::NotInDeck(t) => write!(f, "There aren't that many {} in the deck", t.to_string().trim_end()),
+            MoveError::OpeningUsesBoard => write!(f, "An opening move can't use tiles from the board"),
+            MoveError::OpeningTooLow { points, needed } => write!(f, "Opening melds are worth {points}, {needed} needed")
+        }
+    }
+}
+
+/// Checks that `proposed_board` is a legal result of the player's turn: every meld valid under `rules`, every
+/// previous board tile still there, at least one tile added and only from `player_hand`. Until the player has
+/// `opened`, the added tiles must make up whole melds worth `rules.initial_meld_points`. The previous board is
+/// only known as a list of tiles, so rearranging it before opening can't be caught
+pub fn validate_move(prev: &State, proposed_board: &[Meld], player_hand: &Hand, opened: bool, rules: &RuleSet) -> Result<(), MoveError> {
+    for (index, m) in proposed_board.iter().enumerate() {
+        let checked = match m.kind() {
+            MeldKind::Run => Meld::run_with_rules(m.tiles().to_vec(), rules),
+            MeldKind::Group => Meld::group_with_rules(m.tiles().to_vec(), rules)
+        }.map_err(|error| MoveError::InvalidMeld { index, error })?;
+        if checked.tiles() != m.tiles() && m.kind() == MeldKind::Run {
+            return Err(MoveError::InvalidMeld { index, error: MeldError::NotIncreasing });
+        }
+    }
+
+    let mut added: TileMultiset = proposed_board.iter().flat_map(|m| m.tiles()).collect();
+    let deck = rules.deck();
+    if let Some((t, _)) = added.iter().find(|(t, n)| !deck.contains_at_least(t, *n)) { return Err(MoveError::NotInDeck(t)); }
+    for t in prev.board() {
+        if !added.remove(t) { return Err(MoveError::MissingBoardTile(*t)); }
+    }
+    if added.is_empty() { return Err(MoveError::NothingPlayed); }
+
+    let hand: TileMultiset = player_hand.iter().collect();
+    if let Some((t, _)) = added.iter().find(|(t, n)| !hand.contains_at_least(t, *n)) { return Err(MoveError::NotInHand(t)); }
+
+    if opened { return Ok(()); }
+    let points = best_opening(proposed_board, &mut added, 0).ok_or(MoveError::OpeningUsesBoard)?;
+    if points < rules.initial_meld_points {
+        return Err(MoveError::OpeningTooLow { points, needed: rules.initial_meld_points });
+    }
+    Ok(())
+}
+
+/// The most points from a set of melds, from `melds[from..]`, made of exactly the tiles in `left`
+fn best_opening(melds: &[Meld], left: &mut TileMultiset, from: usize) -> Option<u32> {
+    if left.is_empty() { return Some(0); }
+
+    let mut best = None;
+    for (i, m) in melds.iter().enumerate().skip(from) {
+        let fits = m.tiles().iter().collect::<TileMultiset>().iter().all(|(t, n)| left.contains_at_least(&t, n));
+        if !fits { continue; }
+
+        for t in m.tiles() { left.remove(t); }
+        if let Some(points) = best_opening(melds, left, i + 1) { best = best.max(Some(points + m.points())); }
+        for t in m.tiles() { left.add(*t); }
+    }
+    best
+}
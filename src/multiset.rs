@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use crate::Tile;
+
+/// How many of each tile there are, ignoring order
+pub fn tile_counts(tiles: &[Tile]) -> HashMap<Tile, usize> {
+    let mut counts = HashMap::new();
+    for t in tiles { *counts.entry(*t).or_insert(0) += 1; }
+    counts
+}
+
+/// A bag of tiles, counted by `Tile::ordinal`
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct TileMultiset([usize; Tile::KINDS]);
+
+impl TileMultiset {
+    pub fn new() -> TileMultiset { TileMultiset([0; Tile::KINDS]) }
+
+    pub fn add(&mut self, tile: Tile) { self.0[tile.ordinal() as usize] += 1; }
+
+    /// Removes one copy of the tile, returning whether there was one
+    pub fn remove(&mut self, tile: &Tile) -> bool {
+        let count = &mut self.0[tile.ordinal() as usize];
+        if *count == 0 { return false; }
+        *count -= 1;
+        true
+    }
+
+    pub fn count(&self, tile: &Tile) -> usize { self.0[tile.ordinal() as usize] }
+
+    pub fn contains_at_least(&self, tile: &Tile, n: usize) -> bool { self.count(tile) >= n }
+
+    pub fn len(&self) -> usize { self.0.iter().sum() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Each distinct tile present with its count, in `Ord` order
+    pub fn iter(&self) -> impl Iterator<Item=(Tile, usize)> + '_ {
+        self.0.iter().enumerate().filter(|(_, c)| **c > 0).map(|(o, c)| (Tile::from_ordinal(o as u8).unwrap(), *c))
+    }
+}
+
+impl Default for TileMultiset {
+    fn default() -> Self { TileMultiset::new() }
+}
+
+impl FromIterator<Tile> for TileMultiset {
+    fn from_iter<T: IntoIterator<Item=Tile>>(iter: T) -> Self {
+        let mut set = TileMultiset::new();
+        for t in iter { set.add(t); }
+        set
+    }
+}
+
+impl<'a> FromIterator<&'a Tile> for TileMultiset {
+    fn from_iter<T: IntoIterator<Item=&'a Tile>>(iter: T) -> Self {
+        iter.into_iter().copied().collect()
+    }
+}
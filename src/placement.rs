@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+use crate::{solver, AcceptResult, Meld, State, Tile};
+
+/// A way a tile could be played, holding the meld it would end up in
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Placement {
+    /// Added to the end of a run on the board
+    ExtendRun(Meld),
+    /// Added to a group on the board
+    JoinGroup(Meld),
+    /// Started as a new run along with tiles from the hand
+    NewRun(Meld),
+    /// Started as a new group along with tiles from the hand
+    NewGroup(Meld)
+}
+
+pub fn placement_options(state: &State, tile: Tile) -> Vec<Placement> {
+    let mut options = Vec::new();
+
+    // The board as the solver would lay it out, leaving out anything that can't be melded
+    let (melds, _) = solver::solve_board_subset(&State::from_parts(state.board.clone(), Default::default()));
+    for m in &melds {
+        // Only onto an end, so the jokers already on the board keep what they stand for
+        match m.accepts(&tile) {
+            AcceptResult::RunFront(run) | AcceptResult::RunBack(run) => options.push(Placement::ExtendRun(run)),
+            AcceptResult::Group(group) => options.push(Placement::JoinGroup(group)),
+            AcceptResult::Rejected(_) => {}
+        }
+    }
+
+    let mut hand: Vec<Tile> = state.hand.iter().copied().collect();
+    if let Some(i) = hand.iter().position(|t| *t == tile) { hand.remove(i); }
+    let mut distinct = hand.clone();
+    distinct.dedup();
+
+    for (i, a) in distinct.iter().enumerate() {
+        // A second copy of `a` can only be used if the hand has one
+        let start = if hand.iter().filter(|t| *t == a).count() > 1 { i } else { i + 1 };
+        for b in &distinct[start..] {
+            let tiles = vec![tile, *a, *b];
+            if let Ok(run) = Meld::run(tiles.clone()) { options.push(Placement::NewRun(run)); }
+            if let Ok(group) = Meld::group(tiles) { options.push(Placement::NewGroup(group)); }
+        }
+    }
+
+    // The same meld can be reached from copies of a board meld or hand tile
+    let mut seen = HashSet::new();
+    options.retain(|o| seen.insert(o.clone()));
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::position;
+
+    fn tile(t: &str) -> Tile { Tile::from_str(t).unwrap() }
+
+    #[test]
+    fn runs_extend_past_their_jokers() {
+        let state = position("r5 r6 j", "");
+        assert!(!placement_options(&state, tile("r7")).iter().any(|p| matches!(p, Placement::ExtendRun(_))));
+        let extended = placement_options(&state, tile("r8"));
+        assert_eq!(extended, vec![Placement::ExtendRun(Meld::run(vec![tile("r5"), tile("r6"), Tile::Joker, tile("r8")]).unwrap())]);
+    }
+
+    #[test]
+    fn options_are_listed_once() {
+        let state = position("r1 r2 r3 b9 y9 x9 r1 r2 r3", "r5 r6 r5 r6 b4");
+        let options = placement_options(&state, tile("r4"));
+        assert!(options.iter().enumerate().all(|(i, o)| !options[i + 1..].contains(o)), "{options:?}");
+        assert!(options.contains(&Placement::ExtendRun(Meld::run(vec![tile("r1"), tile("r2"), tile("r3"), tile("r4")]).unwrap())));
+        assert!(options.contains(&Placement::NewRun(Meld::run(vec![tile("r4"), tile("r5"), tile("r6")]).unwrap())));
+    }
+}
@@ -0,0 +1,24 @@
+use crate::{solve, Board, Hand, Meld, Solution, State};
+
+/// A named area of the table. Melds in a locked region stay exactly as they are, while the tiles of every
+/// unlocked region are pooled and can be rearranged together
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Region {
+    pub name: String,
+    pub melds: Vec<Meld>,
+    pub locked: bool
+}
+
+/// Plays from `hand` into the unlocked regions. The solution's melds are the rearranged shared tiles followed by
+/// the locked regions' melds, unchanged. `None` if the shared tiles can't be arranged
+pub fn solve_regions(regions: &[Region], hand: &Hand) -> Option<Solution> {
+    let board: Board = regions.iter()
+        .filter(|r| !r.locked)
+        .flat_map(|r| r.melds.iter().flat_map(|m| m.tiles().iter().copied()))
+        .collect();
+
+    let solution = solve(&State::from_parts(board, hand.clone()))?;
+    let mut melds = solution.melds().to_vec();
+    melds.extend(regions.iter().filter(|r| r.locked).flat_map(|r| r.melds.iter().cloned()));
+    Some(Solution::new(melds, solution.played().to_vec(), solution.hand().to_vec()))
+}
@@ -0,0 +1,95 @@
+use std::io::{BufRead, Write};
+use crate::{SolveError, SolverConfig, State, Tile};
+
+/// Where the interactive loop reads its commands from
+pub trait InputSource {
+    /// The next line typed, or `None` at the end of input
+    fn read_command(&mut self) -> Option<String>;
+}
+
+/// Where the interactive loop writes its prompts and results
+pub trait OutputSink {
+    fn print(&mut self, text: &str);
+
+    fn println(&mut self, text: &str) {
+        self.print(text);
+        self.print("\n");
+    }
+}
+
+impl<R: BufRead> InputSource for R {
+    fn read_command(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line)
+        }
+    }
+}
+
+impl<W: Write> OutputSink for W {
+    /// Flushes each time, so the prompt shows before waiting for input
+    fn print(&mut self, text: &str) {
+        self.write_all(text.as_bytes()).unwrap();
+        self.flush().unwrap();
+    }
+}
+
+/// Runs the interactive loop until `input` runs out, e.g. `run_interactive(&mut stdin().lock(), &mut stdout())`
+pub fn run_interactive(input: &mut impl InputSource, output: &mut impl OutputSink) {
+    let mut state = State::new();
+    let mut last = String::new();
+
+    loop {
+        output.println("\n's' to solve");
+        output.println("Prefix 'b' to add tiles to the board");
+        output.println("Prefix 'h' to add tiles to your hand");
+        output.println("End with '?' to list ways of finishing the last tile, '!!' repeats the last input");
+        output.print("> ");
+
+        // End of input, e.g. from a pipe or Ctrl-D
+        let Some(input) = input.read_command() else { break; };
+
+        let mut input = input.trim().to_string();
+        if input == "!!" {
+            output.println(&format!("> {last}"));
+            input = last.clone();
+        }
+        if input.is_empty() { output.println("Provide an input"); continue; }
+
+        if let Some(partial) = input.strip_suffix('?') {
+            let partial = partial.get(1..).unwrap_or("").rsplit(|c: char| c == ',' || c.is_whitespace()).next().unwrap();
+            let options = Tile::complete_partial(partial);
+            if options.is_empty() { output.println(&format!("Nothing completes '{partial}'")); }
+            else { output.println(&options.join(" ")); }
+            continue;
+        }
+        last = input.clone();
+        let input = input.as_str();
+
+        let code = input.chars().next().unwrap();
+        if code == 'b' {
+            if let Err(e) = state.add_board_line(&input[1..]) { output.println(&e.to_string()) }
+        }
+        else if code == 'h' {
+            if let Err(e) = state.add_hand_line(&input[1..]) { output.println(&e.to_string()) }
+        }
+        else if code == 's' {
+            match state.solve_and_apply(&SolverConfig::default()) {
+                Some(solution) => {
+                    output.println(&solution.format());
+                    output.println(&solution.summary());
+                },
+                // Every meld needs a real tile, so this only happens with jokers on the board
+                None if (&state).into_iter().all(|t| t.is_joker()) => output.println(&SolveError::JokersOnly.to_string()),
+                None => output.println("Board can't be arranged into valid melds")
+            }
+        }
+        else {
+            output.println("Invalid input"); continue;
+        }
+
+        output.println("");
+        output.println(&state.format());
+    }
+}
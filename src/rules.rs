@@ -0,0 +1,62 @@
+use crate::{Colour, Meld, Tile, TileMultiset};
+
+/// The parameters of the game being played. `RuleSet::standard()` is the usual 106 tile game
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RuleSet {
+    pub max_number: u8,
+    pub min_meld_size: usize,
+    /// Capped at the number of colours, as no colour can repeat in a group
+    pub max_group_size: usize,
+    /// Copies of each numbered tile in the deck
+    pub copies: u8,
+    pub jokers: u8,
+    /// The exact tiles in play, for custom puzzles. `None` is `copies` of every numbered tile plus `jokers`
+    pub universe: Option<TileMultiset>,
+    /// Colours whose tiles can also stand in for jokers
+    pub wild_colours: Vec<Colour>,
+    /// The points a player's first move must be worth, from melds of their own tiles
+    pub initial_meld_points: u32
+}
+
+impl RuleSet {
+    pub const fn standard() -> RuleSet {
+        RuleSet {
+            max_number: Meld::MAX_NUMBER,
+            min_meld_size: Meld::MIN_SIZE,
+            max_group_size: Meld::MAX_GROUP_SIZE,
+            copies: 2,
+            jokers: 2,
+            universe: None,
+            wild_colours: Vec::new(),
+            initial_meld_points: 30
+        }
+    }
+
+    /// The standard rules, but with only `universe` in play
+    pub fn with_universe(universe: TileMultiset) -> RuleSet {
+        RuleSet { universe: Some(universe), ..RuleSet::standard() }
+    }
+
+    /// Whether the tile is of a wild colour. Jokers aren't
+    pub fn is_wild(&self, tile: &Tile) -> bool {
+        tile.colour().is_some_and(|c| self.wild_colours.contains(&c))
+    }
+
+    /// Every tile in play
+    pub fn deck(&self) -> TileMultiset {
+        if let Some(universe) = &self.universe { return universe.clone(); }
+
+        let mut deck = TileMultiset::new();
+        for c in Colour::all() {
+            for n in 1..=self.max_number.min(Meld::MAX_NUMBER) {
+                for _ in 0..self.copies { deck.add(Tile::Normal(c, n)); }
+            }
+        }
+        for _ in 0..self.jokers { deck.add(Tile::Joker); }
+        deck
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self { RuleSet::standard() }
+}
@@ -0,0 +1,27 @@
+use crate::{Hand, Tile};
+
+/// Points scored by each player at the end of a round
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ScoreResult {
+    pub winner: i32,
+    /// In the same order as the hands passed to `score_round`
+    pub others: Vec<i32>
+}
+
+/// The value of the tiles left in a hand: each tile's number, with jokers worth `joker_value`
+pub fn hand_penalty(hand: &Hand, joker_value: u8) -> u32 {
+    hand.iter().map(|t| match t {
+        Tile::Normal(_, n) => *n as u32,
+        Tile::Joker => joker_value as u32
+    }).sum()
+}
+
+/// Each loser scores minus the value of their hand, and the winner scores the total of those values. If the
+/// winner didn't go out, e.g. when the pool ran dry, their own hand value is taken off
+pub fn score_round(winner_hand: &Hand, other_hands: &[Hand], joker_value: u8) -> ScoreResult {
+    let others: Vec<i32> = other_hands.iter().map(|h| -(hand_penalty(h, joker_value) as i32)).collect();
+    ScoreResult {
+        winner: -others.iter().sum::<i32>() - hand_penalty(winner_hand, joker_value) as i32,
+        others
+    }
+}
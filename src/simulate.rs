@@ -0,0 +1,142 @@
+use std::cmp::Reverse;
+#[cfg(feature = "parallel")]
+use std::thread;
+use crate::{solve, solve_with_config, RuleSet, SolverConfig, State, Tile};
+
+/// How playing changes after drawing, averaged over random draws from the unseen tiles
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DrawStats {
+    pub trials: usize,
+    /// Hand tiles playable before drawing
+    pub baseline: usize,
+    /// Extra hand tiles playable after drawing, old hand and drawn tiles alike
+    pub mean_increase: f64,
+    pub max_increase: usize,
+    /// Trials where drawing let more tiles be played
+    pub improved: usize
+}
+
+/// SplitMix64 - small, and good enough to shuffle a pool of tiles
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize { (self.next() % n as u64) as usize }
+}
+
+/// Runs `work(t, threads)` for each of up to `jobs` threads and joins their results in order. Runs it once on this
+/// thread without the `parallel` feature, or if there's only one thread to use
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn spread<R: Send>(jobs: usize, work: impl Fn(usize, usize) -> Vec<R> + Sync) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    {
+        let threads = thread::available_parallelism().map_or(1, |t| t.get()).min(jobs.max(1));
+        if threads > 1 {
+            return thread::scope(|scope| {
+                let handles: Vec<_> = (0..threads).map(|t| {
+                    let work = &work;
+                    scope.spawn(move || work(t, threads))
+                }).collect();
+                handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+            });
+        }
+    }
+    work(0, 1)
+}
+
+fn played(state: &State) -> usize {
+    solve(state).map_or(0, |s| s.played().len())
+}
+
+/// Each trial is seeded from `seed` and its own index, so the result doesn't depend on how trials are split
+/// across threads
+fn trial(state: &State, unseen: &[Tile], n: usize, seed: u64, index: usize) -> usize {
+    let mut rng = Rng(seed ^ (index as u64).wrapping_mul(0xD1B54A32D192ED03));
+    let mut pool = unseen.to_vec();
+    let draws = n.min(pool.len());
+    for i in 0..draws {
+        let j = i + rng.below(pool.len() - i);
+        pool.swap(i, j);
+    }
+
+    let mut hand = state.hand.clone();
+    hand.extend(pool[..draws].iter().copied());
+    played(&State::from_parts(state.board.clone(), hand))
+}
+
+pub fn simulate_draws(state: &State, n: usize, trials: usize, rules: &RuleSet, seed: u64) -> DrawStats {
+    let baseline = played(state);
+    let unseen = state.unseen(rules);
+
+    let results = spread(trials, |t, threads| {
+        (t..trials).step_by(threads).map(|i| trial(state, &unseen, n, seed, i)).collect()
+    });
+
+    let increases: Vec<usize> = results.iter().map(|p| p.saturating_sub(baseline)).collect();
+    DrawStats {
+        trials,
+        baseline,
+        mean_increase: if trials == 0 { 0.0 } else { increases.iter().sum::<usize>() as f64 / trials as f64 },
+        max_increase: increases.iter().copied().max().unwrap_or(0),
+        improved: increases.iter().filter(|&&i| i > 0).count()
+    }
+}
+
+pub fn rank_draws(state: &State, rules: &RuleSet, max_nodes: Option<u64>) -> Vec<(Tile, u32)> {
+    let config = SolverConfig { max_nodes, ..SolverConfig::default() };
+    let played = |state: &State| solve_with_config(state, &config).map_or(0, |s| s.played().len());
+    let baseline = played(state);
+    // Unseen tiles come in order, so copies are together
+    let mut tiles = state.unseen(rules);
+    tiles.dedup();
+
+    let mut ranked = spread(tiles.len(), |t, threads| {
+        tiles.iter().skip(t).step_by(threads)
+            .map(|tile| (*tile, played(&state.with_hand_tile(*tile)).saturating_sub(baseline) as u32))
+            .collect()
+    });
+
+    ranked.sort_by_key(|(t, gain)| (Reverse(*gain), *t));
+    ranked
+}
+
+pub fn draws_until_playable(state: &State, rules: &RuleSet, seed: u64, max: usize) -> Option<usize> {
+    let mut rng = Rng(seed);
+    let mut pool = state.unseen(rules);
+    let mut state = state.clone();
+
+    for draws in 0..=max.min(pool.len()) {
+        if !state.is_stuck() { return Some(draws); }
+        if draws == max || pool.is_empty() { break; }
+
+        let i = rng.below(pool.len());
+        state.add_to_hand(pool.swap_remove(i));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spread_covers_every_job_once() {
+        let mut done = spread(37, |t, threads| (t..37).step_by(threads).collect());
+        done.sort();
+        assert_eq!(done, (0..37).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn draws_only_depend_on_seed() {
+        let state = crate::examples::sample_with_hand();
+        let rules = RuleSet::standard();
+        assert_eq!(simulate_draws(&state, 2, 8, &rules, 7), simulate_draws(&state, 2, 8, &rules, 7));
+    }
+}
@@ -0,0 +1,365 @@
+use std::fmt;
+use crate::display::DEFAULT_CONFIG;
+use crate::{Meld, MeldError, MeldKind, RuleSet, State, Tile, TileMultiset};
+
+/// The result of a solve: the melds on the board and what happened to each hand tile
+#[derive(Debug)]
+pub struct Solution {
+    melds: Vec<Meld>,
+    played: Vec<Tile>,
+    pub(crate) hand: Vec<Tile>,
+    pub(crate) budget_exhausted: bool,
+    pub(crate) appended: Option<usize>,
+    pub(crate) disturbed_melds: Option<usize>,
+    /// The input's joker labels, given to the melds' jokers in order
+    pub(crate) joker_labels: Vec<Option<u16>>
+}
+
+impl Solution {
+    pub(crate) fn new(melds: Vec<Meld>, played: Vec<Tile>, hand: Vec<Tile>) -> Solution {
+        Solution { melds, played, hand, budget_exhausted: false, appended: None, disturbed_melds: None, joker_labels: Vec::new() }
+    }
+
+    /// Puts the wild tiles the solver played as jokers into joker positions, in order
+    pub(crate) fn fill_wilds(mut self, fillers: Vec<Tile>) -> Solution {
+        if fillers.is_empty() { return self; }
+        let mut fillers = fillers.into_iter();
+        self.melds = self.melds.into_iter().map(|m| m.fill_jokers(&mut fillers)).collect();
+        self
+    }
+
+    pub fn melds(&self) -> &[Meld] { &self.melds }
+    /// Whether the search stopped at `SolverConfig::max_nodes` or `SolverConfig::time_limit`, so this is the best
+    /// found rather than the best
+    pub fn budget_exhausted(&self) -> bool { self.budget_exhausted }
+    /// Tiles added to `SolverConfig::board_melds` when solved with `Objective::AppendOnly`
+    pub fn appended(&self) -> Option<usize> { self.appended }
+    /// `disturbed` on `SolverConfig::board_melds`, when solved with `Objective::MinimalRearrangement`
+    pub fn disturbed_melds(&self) -> Option<usize> { self.disturbed_melds }
+    /// Hand tiles placed on the board
+    pub fn played(&self) -> &[Tile] { &self.played }
+    /// Hand tiles left over
+    pub fn hand(&self) -> &[Tile] { &self.hand }
+
+    /// Each meld on its own line. Jokers show any labels they were read with, e.g. `j1`
+    pub fn format(&self) -> String {
+        let mut string = String::new();
+        let mut labels = self.joker_labels.as_slice();
+        for m in &self.melds {
+            string += &Tile::format_list_labelled(m.tiles(), &DEFAULT_CONFIG, labels);
+            labels = &labels[m.tiles().iter().filter(|t| t.is_joker()).count().min(labels.len())..];
+        }
+
+        string
+    }
+
+    /// Each meld on its own line as boxed tiles after its kind, e.g. `Run:   [r5][J→r6][r7]`, with jokers showing
+    /// any label and the tile they stand for
+    pub fn to_ascii_art(&self) -> String {
+        let mut string = String::new();
+        let mut labels = self.joker_labels.iter().copied();
+        for m in &self.melds {
+            string += match m.kind() {
+                MeldKind::Run => "Run:   ",
+                MeldKind::Group => "Group: "
+            };
+            for r in m.resolve() {
+                string += &match r.tile() {
+                    Tile::Joker => {
+                        let label = labels.next().flatten().map(|l| l.to_string()).unwrap_or_default();
+                        format!("[J{label}\u{2192}{}{}]", r.colour().get_char(), r.number())
+                    },
+                    t => format!("[{}]", t.to_string().trim_end())
+                };
+            }
+            string.push('\n');
+        }
+        string
+    }
+
+    /// A one-line description of the move, e.g. `Played from hand: r7 y7. Rearranged board: r5 r6 r7, y7 b7 x7`,
+    /// listing the melds the played tiles ended up in
+    pub fn summary(&self) -> String {
+        if self.played.is_empty() { return "No tiles played from hand".to_string(); }
+
+        let changed: Vec<String> = self.melds.iter().zip(self.played_positions())
+            .filter(|(_, from_hand)| from_hand.contains(&true))
+            .map(|(m, _)| inline(m.tiles()))
+            .collect();
+
+        format!("Played from hand: {}. Rearranged board: {}", inline(&self.played), changed.join(", "))
+    }
+
+    /// One way of building the solution from the input: each meld is first gathered from board tiles, then has
+    /// its hand tiles played into it
+    pub fn steps(&self) -> Vec<SolveStep> {
+        let mut steps = Vec::new();
+        for (i, (m, from_hand)) in self.melds.iter().zip(self.played_positions()).enumerate() {
+            let board: Vec<Tile> = m.tiles().iter().zip(&from_hand).filter(|(_, h)| !**h).map(|(t, _)| *t).collect();
+            if !board.is_empty() { steps.push(SolveStep::Gather { meld: i, kind: m.kind(), tiles: board }); }
+
+            for (position, (t, _)) in m.tiles().iter().zip(&from_hand).enumerate().filter(|(_, (_, h))| **h) {
+                steps.push(SolveStep::Play { meld: i, position, tile: *t });
+            }
+        }
+        steps
+    }
+
+    /// An order to move tiles in on a real table. Melds made only from the hand come first, so the board is only
+    /// touched once they're down. Each meld's moves are contiguous, board tiles before hand tiles, so at most one
+    /// meld is half built at any time
+    pub fn move_order(&self) -> Vec<TileMove> {
+        let played = self.played_positions();
+        let mut order: Vec<usize> = (0..self.melds.len()).collect();
+        // Stable, so melds stay in solution order otherwise
+        order.sort_by_key(|&i| !played[i].iter().all(|p| *p));
+
+        let mut moves = Vec::new();
+        for i in order {
+            let tiles = self.melds[i].tiles().iter().zip(&played[i]);
+            for from_hand in [false, true] {
+                moves.extend(tiles.clone().filter(|(_, p)| **p == from_hand).map(|(t, _)| TileMove { tile: *t, from_hand, meld: i }));
+            }
+        }
+        moves
+    }
+
+    /// For each meld, which of its tiles are the played hand tiles
+    fn played_positions(&self) -> Vec<Vec<bool>> {
+        let mut unplaced: TileMultiset = self.played.iter().collect();
+        self.melds.iter().map(|m| m.tiles().iter().map(|t| unplaced.remove(t)).collect()).collect()
+    }
+
+    /// Jokers in melds that a tile left in hand could have stood in for, each hand tile only counted once
+    pub fn joker_waste(&self) -> usize {
+        let mut spare = self.hand.clone();
+        self.melds.iter().flat_map(|m| m.resolve()).filter(|r| {
+            let Some(i) = spare.iter().position(|t| {
+                r.joker_alternatives().iter().any(|(c, n)| *t == Tile::Normal(*c, *n))
+            }) else { return false; };
+            spare.remove(i);
+            true
+        }).count()
+    }
+
+    /// How many of `board_melds`, the melds the board was laid out in, don't appear unchanged in the solution
+    pub fn disturbed(&self, board_melds: &[Meld]) -> usize {
+        let sorted = |m: &Meld| {
+            let mut tiles = m.tiles().to_vec();
+            tiles.sort();
+            tiles
+        };
+        let mut melds: Vec<Vec<Tile>> = self.melds.iter().map(sorted).collect();
+
+        board_melds.iter().filter(|m| {
+            let tiles = sorted(m);
+            match melds.iter().position(|p| *p == tiles) {
+                Some(i) => { melds.swap_remove(i); false },
+                None => true
+            }
+        }).count()
+    }
+
+    /// The melds that differ from `other`'s, one per line: `- ` for melds only in this solution, `+ ` for ones only
+    /// in `other`, and `~ old -> new` where a meld gained or lost tiles. Melds are compared as sets of tiles, so
+    /// order doesn't matter. Empty if the boards are the same
+    pub fn diff(&self, other: &Solution) -> String {
+        let sorted = |m: &Meld| {
+            let mut tiles = m.tiles().to_vec();
+            tiles.sort();
+            tiles
+        };
+        let mut added: Vec<&Meld> = other.melds.iter().collect();
+        let mut removed = Vec::new();
+        for m in &self.melds {
+            match added.iter().position(|a| sorted(a) == sorted(m)) {
+                Some(i) => { added.swap_remove(i); },
+                None => removed.push(m)
+            }
+        }
+
+        let shared = |a: &Meld, b: &Meld| {
+            let b: TileMultiset = b.tiles().iter().collect();
+            let mut a: TileMultiset = a.tiles().iter().collect();
+            b.iter().map(|(t, n)| (0..n).filter(|_| a.remove(&t)).count()).sum::<usize>()
+        };
+        let mut diff = String::new();
+        for m in removed {
+            // The added meld with the most tiles in common is taken to be what this one became
+            let closest = added.iter().enumerate()
+                .map(|(i, a)| (shared(m, a), i))
+                .filter(|(n, _)| *n > 0)
+                .max();
+            match closest {
+                Some((_, i)) => diff += &format!("~ {} -> {}\n", inline(m.tiles()), inline(added.remove(i).tiles())),
+                None => diff += &format!("- {}\n", inline(m.tiles()))
+            }
+        }
+        for m in added { diff += &format!("+ {}\n", inline(m.tiles())); }
+        diff
+    }
+
+    /// Joins complete runs of the same colour that continue one another, e.g. `r1 r2 r3` and `r4 r5 r6`
+    pub fn merge_runs(&mut self) {
+        let mut i = 0;
+        while i < self.melds.len() {
+            let mut merged = false;
+
+            for j in 0..self.melds.len() {
+                if i == j { continue; }
+
+                if let (Some((c, _, max)), Some((other_c, other_min, _))) = (self.melds[i].run_range(), self.melds[j].run_range()) {
+                    if c != other_c || max + 1 != other_min { continue; }
+
+                    let other = self.melds.remove(j);
+                    if j < i { i -= 1; }
+                    let mut tiles = self.melds[i].tiles().to_vec();
+                    tiles.extend_from_slice(other.tiles());
+                    self.melds[i] = Meld::new_unchecked(tiles, MeldKind::Run, &RuleSet::standard());
+                    merged = true;
+                    break;
+                }
+            }
+
+            if !merged { i += 1; }
+        }
+    }
+
+    /// Checks the solution against the position it came from: every meld is valid, the melds hold exactly the
+    /// board tiles plus the played tiles, and the played and remaining tiles are exactly the hand
+    pub fn verify(&self, input: &State) -> Result<(), VerifyError> {
+        self.verify_with_rules(input, &RuleSet::standard())
+    }
+
+    /// `verify`, with the melds checked against `rules`
+    pub fn verify_with_rules(&self, input: &State, rules: &RuleSet) -> Result<(), VerifyError> {
+        for (i, m) in self.melds.iter().enumerate() {
+            // Wild tiles are checked as the jokers they stand in for
+            let view = m.joker_view();
+            let checked = match m.kind() {
+                MeldKind::Run => Meld::run_with_rules(view.clone(), rules),
+                MeldKind::Group => Meld::group_with_rules(view.clone(), rules)
+            };
+            let checked = checked.map_err(|error| VerifyError::InvalidMeld { index: i, error })?;
+            // A run is only valid in the order it's laid out, as that's what decides the jokers' values
+            if checked.tiles() != view && m.kind() == MeldKind::Run {
+                return Err(VerifyError::InvalidMeld { index: i, error: MeldError::NotIncreasing });
+            }
+        }
+
+        let melded: TileMultiset = self.melds.iter().flat_map(|m| m.tiles()).collect();
+        let expected: TileMultiset = input.board().iter().chain(&self.played).collect();
+        for o in 0..Tile::KINDS {
+            let tile = Tile::from_ordinal(o as u8).unwrap();
+            if melded.count(&tile) > expected.count(&tile) { return Err(VerifyError::ExtraTile(tile)); }
+            if melded.count(&tile) < expected.count(&tile) {
+                return Err(if input.board().contains(&tile) { VerifyError::MissingBoardTile(tile) } else { VerifyError::MissingPlayedTile(tile) });
+            }
+        }
+
+        let accounted: TileMultiset = self.played.iter().chain(&self.hand).collect();
+        let hand: TileMultiset = input.hand().iter().collect();
+        for o in 0..Tile::KINDS {
+            let tile = Tile::from_ordinal(o as u8).unwrap();
+            if accounted.count(&tile) > hand.count(&tile) { return Err(VerifyError::NotInHand(tile)); }
+            if accounted.count(&tile) < hand.count(&tile) { return Err(VerifyError::LostHandTile(tile)); }
+        }
+
+        Ok(())
+    }
+}
+
+/// Tiles on one line, without `format_list`'s wrapping
+fn inline(tiles: &[Tile]) -> String {
+    tiles.iter().map(|t| t.to_string()).collect::<String>().trim_end().to_string()
+}
+
+/// Putting a tile into meld `meld` of the solution, from `Solution::move_order`
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TileMove {
+    pub tile: Tile,
+    /// Whether the tile comes from the hand rather than elsewhere on the board
+    pub from_hand: bool,
+    pub meld: usize
+}
+
+/// A single change from `Solution::steps`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SolveStep {
+    /// Brings board tiles together, in order, to start meld `meld`
+    Gather { meld: usize, kind: MeldKind, tiles: Vec<Tile> },
+    /// Puts a hand tile into meld `meld` at `position` of its final layout
+    Play { meld: usize, position: usize, tile: Tile }
+}
+
+impl fmt::Display for SolveStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveStep::Gather { meld, kind, tiles } => {
+                let kind = match kind { MeldKind::Run => "run", MeldKind::Group => "group" };
+                write!(f, "Form {kind} {meld} from {}", inline(tiles))
+            },
+            SolveStep::Play { meld, tile, .. } => write!(f, "Add {}from hand to meld {meld}", tile.to_string())
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum VerifyError {
+    InvalidMeld { index: usize, error: MeldError },
+    /// A board tile that isn't in any meld
+    MissingBoardTile(Tile),
+    /// A played tile that isn't in any meld
+    MissingPlayedTile(Tile),
+    /// A meld tile that's neither on the board nor played, or is used more times than it's available
+    ExtraTile(Tile),
+    /// A played or remaining hand tile that wasn't in the hand
+    NotInHand(Tile),
+    /// A hand tile that's neither played nor still in hand
+    LostHandTile(Tile)
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidMeld { index, error } => write!(f, "Meld {index} is invalid: {error}"),
+            VerifyError::MissingBoardTile(t) => write!(f, "Board tile {}isn't in a meld", t.to_string()),
+            VerifyError::MissingPlayedTile(t) => write!(f, "Played tile {}isn't in a meld", t.to_string()),
+            VerifyError::ExtraTile(t) => write!(f, "Tile {}is melded more times than it's available", t.to_string()),
+            VerifyError::NotInHand(t) => write!(f, "Tile {}wasn't in the hand", t.to_string()),
+            VerifyError::LostHandTile(t) => write!(f, "Hand tile {}went missing", t.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solve;
+    use crate::examples::{position, tiles};
+
+    #[test]
+    fn merges_contiguous_runs() {
+        let melds = vec![Meld::run(tiles("r4 r5 r6")).unwrap(), Meld::run(tiles("r1 r2 r3")).unwrap()];
+        let mut solution = Solution::new(melds, Vec::new(), Vec::new());
+        solution.merge_runs();
+        assert_eq!(solution.melds().len(), 1);
+        assert_eq!(solution.melds()[0].tiles(), tiles("r1 r2 r3 r4 r5 r6"));
+    }
+
+    #[test]
+    fn keeps_runs_apart_across_a_gap_or_colour() {
+        let melds = vec![Meld::run(tiles("r1 r2 r3")).unwrap(), Meld::run(tiles("r5 r6 r7")).unwrap(), Meld::run(tiles("b4 b5 b6")).unwrap()];
+        let mut solution = Solution::new(melds, Vec::new(), Vec::new());
+        solution.merge_runs();
+        assert_eq!(solution.melds().len(), 3);
+    }
+
+    #[test]
+    fn solver_output_is_merged() {
+        let state = position("r1 r2 r3 r4 r5 r6", "");
+        let solution = solve(&state).unwrap();
+        assert_eq!(solution.melds().len(), 1);
+        assert_eq!(solution.melds()[0].tiles(), tiles("r1 r2 r3 r4 r5 r6"));
+    }
+}
@@ -1,290 +1,1125 @@
-use std::collections::{HashSet, VecDeque};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use crate::{Colour, State, Tile};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::{append, Colour, Hand, Meld, MeldKind, RuleSet, Solution, State, Tile, TileMultiset};
 
-#[derive(Hash)]
+#[derive(Clone)]
+pub struct SolverConfig {
+    /// Optimised in order, each only deciding between arrangements that tie on all the ones before it
+    pub objectives: Vec<Objective>,
+    /// Hand tiles to hold on to for `Objective::KeepPreferred`, e.g. jokers
+    pub prefer_keep: Vec<Tile>,
+    /// How the board is laid out now, for `Objective::MinimalRearrangement` and `Objective::AppendOnly`
+    pub board_melds: Vec<Meld>,
+    /// Tiles that must end up in a meld of the given kind. Pinning a tile twice needs two copies of it there
+    pub pinned: Vec<(Tile, MeldKind)>,
+    /// Melds that can be broken up, but not grown. No meld in the solution may hold all of one of these' tiles
+    /// and more
+    pub frozen_length: Vec<Meld>,
+    /// Called at most once every `progress_interval` while searching
+    pub on_progress: Option<Arc<dyn Fn(SolveProgress) + Send + Sync>>,
+    pub progress_interval: Duration,
+    /// Called at the key points of a search, e.g. to pass them on to a logger
+    pub on_event: Option<Arc<dyn Fn(SolveEvent) + Send + Sync>>,
+    /// Called for every branch of the search and how it ended, in depth-first order. Slows solving down a lot
+    pub on_trace: Option<Arc<dyn Fn(TraceNode) + Send + Sync>>,
+    /// Only `wild_colours`, `max_number`, `min_meld_size`, `max_group_size` and the tiles of `deck` are used so far.
+    /// A position holding more of a tile than the deck does has no solution
+    pub rules: RuleSet,
+    /// Stops searching after this many nodes and returns the best so far. Unlike a time limit, it cuts off in the
+    /// same place on every machine. `solve_budgeted` tells a cut off search apart from a finished one
+    pub max_nodes: Option<u64>,
+    /// Stops searching once this much wall-clock time has passed and returns the best so far, checked every
+    /// thousand or so nodes
+    pub time_limit: Option<Duration>
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            objectives: vec![Objective::MaxTilesPlayed, Objective::KeepPreferred],
+            prefer_keep: Vec::new(),
+            board_melds: Vec::new(),
+            pinned: Vec::new(),
+            frozen_length: Vec::new(),
+            on_progress: None,
+            progress_interval: Duration::from_millis(100),
+            on_event: None,
+            on_trace: None,
+            rules: RuleSet::standard(),
+            max_nodes: None,
+            time_limit: None
+        }
+    }
+}
+
+impl fmt::Debug for SolverConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SolverConfig")
+            .field("objectives", &self.objectives)
+            .field("prefer_keep", &self.prefer_keep)
+            .field("board_melds", &self.board_melds)
+            .field("pinned", &self.pinned)
+            .field("frozen_length", &self.frozen_length)
+            .field("on_progress", &self.on_progress.as_ref().map(|_| ".."))
+            .field("progress_interval", &self.progress_interval)
+            .field("on_event", &self.on_event.as_ref().map(|_| ".."))
+            .field("on_trace", &self.on_trace.as_ref().map(|_| ".."))
+            .field("rules", &self.rules)
+            .field("max_nodes", &self.max_nodes)
+            .field("time_limit", &self.time_limit)
+            .finish()
+    }
+}
+
+/// Something to optimise. `SolverConfig::objectives` ranks them, with later ones only breaking ties
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Objective {
+    /// Play as many hand tiles as possible
+    MaxTilesPlayed,
+    /// Put as few jokers into melds as possible
+    MinJokersUsed,
+    /// Favour long runs, scoring the sum of the squared run lengths
+    PreferLongerRuns,
+    /// Keep as many of `SolverConfig::prefer_keep` in hand as possible
+    KeepPreferred,
+    /// Leave as few tiles in hand as possible, which is `Solution::hand`. Ranks the same as `MaxTilesPlayed`
+    MinHandRemaining,
+    /// Break up as few of `SolverConfig::board_melds` as possible, as measured by `Solution::disturbed`. Runs
+    /// aren't merged when this is used, so that untouched melds stay as they were
+    MinimalRearrangement,
+    /// Keep as many pairs of the same real tile in hand as possible, to build groups from later
+    KeepPairs,
+    /// Break up none of `SolverConfig::board_melds`: each stays whole in a meld of its own, which only gains tiles
+    /// at a run's ends or a group's missing colours, and anything else is a new meld. Scores how many are broken
+    /// up, so goes first to forbid it. `Solution::appended` says how many tiles were added to them
+    AppendOnly
+}
+
+impl Objective {
+    fn score(&self, state: &SolvingState, targets: &Targets) -> i64 {
+        match self {
+            Objective::MaxTilesPlayed => state.played.len() as i64,
+            Objective::MinJokersUsed => -(state.jokers_placed as i64),
+            Objective::PreferLongerRuns => state.melds.iter()
+                .filter(|m| m.kind() == MeldKind::Run)
+                .map(|m| (m.tiles().len() * m.tiles().len()) as i64)
+                .sum(),
+            Objective::KeepPreferred => {
+                let kept = SolvingState::counts(&state.kept);
+                (0..Tile::KINDS).map(|i| targets.prefer[i].min(kept[i]) as i64).sum()
+            },
+            Objective::MinHandRemaining => -(state.kept.len() as i64),
+            Objective::MinimalRearrangement => -(targets.broken(state, None) as i64),
+            Objective::KeepPairs => {
+                let kept = SolvingState::counts(&state.kept);
+                (0..Tile::JOKER_ORDINAL as usize).map(|i| (kept[i] / 2) as i64).sum()
+            },
+            Objective::AppendOnly => -(targets.not_appended(state, None) as i64)
+        }
+    }
+
+    fn bound(&self, state: &SolvingState, targets: &Targets) -> i64 {
+        match self {
+            Objective::MaxTilesPlayed => (state.played.len() + state.remaining_hand) as i64,
+            Objective::MinJokersUsed => -(state.jokers_placed as i64),
+            Objective::PreferLongerRuns => i64::MAX,
+            Objective::KeepPreferred => {
+                let kept = SolvingState::counts(&state.kept);
+                (0..Tile::KINDS).map(|i| targets.prefer[i].min(kept[i] + state.hand[i]) as i64).sum()
+            },
+            Objective::MinHandRemaining => -(state.kept.len() as i64),
+            Objective::MinimalRearrangement => -(targets.broken(state, state.anchor()) as i64),
+            Objective::KeepPairs => {
+                let kept = SolvingState::counts(&state.kept);
+                (0..Tile::JOKER_ORDINAL as usize).map(|i| ((kept[i] + state.hand[i]) / 2) as i64).sum()
+            },
+            Objective::AppendOnly => -(targets.not_appended(state, state.anchor()) as i64)
+        }
+    }
+}
+
+/// Something that happened during a search. `Improved` can fire many times, so suits a lower log level than the rest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveEvent {
+    Started { board: usize, hand: usize, objectives: Vec<Objective> },
+    /// A better arrangement was found, scoring `score` - board tiles placed, then each objective
+    Improved { nodes: u64, score: Vec<i64> },
+    /// `SolverConfig::max_nodes` was reached, comes just before `Finished`
+    BudgetExhausted { nodes: u64 },
+    /// `SolverConfig::time_limit` was reached, comes just before `Finished`
+    TimedOut { nodes: u64, elapsed: Duration },
+    Finished { nodes: u64, elapsed: Duration, found: bool }
+}
+
+impl fmt::Display for SolveEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveEvent::Started { board, hand, objectives } =>
+                write!(f, "Solving {board} board and {hand} hand tiles for {objectives:?}"),
+            SolveEvent::Improved { nodes, score } => write!(f, "Found arrangement scoring {score:?} after {nodes} nodes"),
+            SolveEvent::BudgetExhausted { nodes } => write!(f, "Stopped at the {nodes} node budget"),
+            SolveEvent::TimedOut { nodes, elapsed } => write!(f, "Stopped at the time limit after {nodes} nodes, {elapsed:?}"),
+            SolveEvent::Finished { nodes, elapsed, found: true } => write!(f, "Solved in {nodes} nodes, {elapsed:?}"),
+            SolveEvent::Finished { nodes, elapsed, found: false } => write!(f, "No arrangement after {nodes} nodes, {elapsed:?}")
+        }
+    }
+}
+
+/// One step of the search tree, from `SolverConfig::on_trace`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceNode {
+    /// Branches taken to get here
+    pub depth: usize,
+    pub decision: TraceDecision,
+    /// The best any arrangement below here could score - board tiles placed, then each objective
+    pub bound: Vec<i64>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceDecision {
+    /// The smallest undecided tile goes into this meld
+    Place(Meld),
+    /// The smallest undecided tile is left in hand, or off the board
+    Skip(Tile),
+    /// The smallest undecided tile is wild and is played as a joker in a meld placed earlier or later
+    Fill(Tile),
+    /// Nothing below can beat the best arrangement so far
+    Pruned,
+    /// Every tile is decided on, giving this score
+    Leaf { score: Vec<i64>, best: bool },
+    /// Every tile is decided on, but jokers were left on the board or a pin wasn't honoured
+    Invalid
+}
+
+impl fmt::Display for TraceNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", "  ".repeat(self.depth))?;
+        match &self.decision {
+            TraceDecision::Place(m) => write!(f, "place {}", m.tiles().iter().map(|t| t.to_string()).collect::<String>().trim_end()),
+            TraceDecision::Skip(t) => write!(f, "skip {}", t.to_string().trim_end()),
+            TraceDecision::Fill(t) => write!(f, "fill {}", t.to_string().trim_end()),
+            TraceDecision::Pruned => write!(f, "pruned"),
+            TraceDecision::Leaf { score, best: true } => write!(f, "leaf {score:?}, new best"),
+            TraceDecision::Leaf { score, best: false } => write!(f, "leaf {score:?}"),
+            TraceDecision::Invalid => write!(f, "invalid")
+        }?;
+        write!(f, " (bound {:?})", self.bound)
+    }
+}
+
+/// What the objectives are measured against, worked out once per search
+struct Targets {
+    prefer: TileCounts,
+    /// Each of `SolverConfig::board_melds` as sorted tiles, with the ordinal of its smallest real tile
+    board_melds: Vec<(usize, Vec<Tile>)>,
+    layout: Vec<Meld>,
+    rules: RuleSet
+}
+
+impl Targets {
+    fn new(config: &SolverConfig) -> Targets {
+        Targets {
+            prefer: SolvingState::counts(&config.prefer_keep),
+            board_melds: config.board_melds.iter().map(|m| {
+                let mut tiles = m.tiles().to_vec();
+                tiles.sort();
+                (tiles[0].ordinal() as usize, tiles)
+            }).collect(),
+            layout: config.board_melds.clone(),
+            rules: config.rules.clone()
+        }
+    }
+
+    /// Board melds that don't sit unchanged in a meld of their own. With an anchor, only counts those whose
+    /// smallest real tile is before it, as the meld holding them has been placed already
+    fn not_appended(&self, state: &SolvingState, anchor: Option<usize>) -> usize {
+        let settled: Vec<&Meld> = self.layout.iter().zip(&self.board_melds)
+            .filter(|(_, (first, _))| anchor.is_none_or(|a| *first < a))
+            .map(|(m, _)| m)
+            .collect();
+        append::match_board_melds(&settled, &state.melds, &self.rules).iter().filter(|m| m.is_none()).count()
+    }
+
+    /// Board melds that haven't been formed again. With an anchor, only counts those that can't be formed any
+    /// more, as every meld placed from here on has its smallest real tile at or after the anchor
+    fn broken(&self, state: &SolvingState, anchor: Option<usize>) -> usize {
+        let mut placed: Vec<Vec<Tile>> = state.melds.iter().map(|m| {
+            let mut tiles = m.tiles().to_vec();
+            tiles.sort();
+            tiles
+        }).collect();
+
+        self.board_melds.iter().filter(|(first, tiles)| {
+            if let Some(i) = placed.iter().position(|p| p == tiles) {
+                placed.swap_remove(i);
+                return false;
+            }
+            anchor.is_none_or(|a| *first < a)
+        }).count()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SolveProgress {
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Hand tiles played by the best arrangement found so far
+    pub best_played: Option<usize>
+}
+
+/// Tiles still to be placed, counted by `Tile::ordinal` so that copies of the same tile are interchangeable
+type TileCounts = [u8; Tile::KINDS];
+
+/// A score for `solve_with` to maximise, given the melds and the hand tiles left over
+type CustomObjective<'a> = dyn Fn(&[Meld], &Hand) -> i64 + 'a;
+
+#[derive(Clone)]
 pub struct SolvingState {
-    unused_board_tiles: VecDeque<Tile>,
-    unused_hand_tiles: VecDeque<Tile>,
-    runs: Vec<Run>
+    board: TileCounts,
+    hand: TileCounts,
+    remaining_board: usize,
+    remaining_hand: usize,
+    melds: Vec<Meld>,
+    played: Vec<Tile>,
+    kept: Vec<Tile>,
+    removed: Vec<Tile>,
+    placed: usize,
+    jokers_placed: usize,
+    /// Tiles of `RuleSet::wild_colours`, which can be played as themselves or as jokers
+    wild: [bool; Tile::KINDS],
+    /// Joker positions in `melds`. Each is filled by a wild tile from `fillers` or, once the real tiles are all
+    /// decided, by a real joker
+    slots: usize,
+    fillers: Vec<Tile>
 }
 
 impl SolvingState {
+    /// Only the count of each tile is kept, so the search - and so the solution - is the same however the tiles
+    /// were added
     pub fn from_state(state: &State) -> SolvingState {
-        let mut unused_board_tiles = VecDeque::with_capacity(state.board.len());
-        for t in &state.board {
-            unused_board_tiles.push_back(t.clone());
-        }
-        let mut unused_hand_tiles = VecDeque::with_capacity(state.hand.len());
-        for t in &state.hand {
-            unused_hand_tiles.push_back(t.clone());
+        let mut board = [0; Tile::KINDS];
+        for t in &state.board { board[t.ordinal() as usize] += 1; }
+        let mut hand = [0; Tile::KINDS];
+        for t in &state.hand { hand[t.ordinal() as usize] += 1; }
+
+        SolvingState {
+            board,
+            hand,
+            remaining_board: state.board.len(),
+            remaining_hand: state.hand.len(),
+            melds: Vec::new(),
+            played: Vec::new(),
+            kept: Vec::new(),
+            removed: Vec::new(),
+            placed: 0,
+            jokers_placed: 0,
+            wild: [false; Tile::KINDS],
+            slots: 0,
+            fillers: Vec::new()
         }
+    }
+
+    fn with_rules(mut self, rules: &RuleSet) -> SolvingState {
+        for (i, w) in self.wild.iter_mut().enumerate() { *w = rules.is_wild(&Tile::from_ordinal(i as u8).unwrap()); }
+        self
+    }
+
+    fn wilds_available(&self) -> usize {
+        (0..Tile::JOKER_ORDINAL as usize).filter(|&i| self.wild[i]).map(|i| self.available(i) as usize).sum()
+    }
+
+    /// Jokers a meld built on `anchor` could still use: real jokers, and wild tiles other than the anchor
+    fn spare_jokers(&self, anchor: usize) -> usize {
+        let wilds = self.wilds_available() - self.wild[anchor] as usize;
+        (self.available(Tile::JOKER_ORDINAL as usize) as usize + self.fillers.len() + wilds).saturating_sub(self.slots)
+    }
 
-        SolvingState { unused_board_tiles, unused_hand_tiles, runs: Vec::new() }
+    /// Whether there's still something to fill every joker position with
+    fn slots_fillable(&self) -> bool {
+        self.slots <= self.available(Tile::JOKER_ORDINAL as usize) as usize + self.fillers.len() + self.wilds_available()
     }
 
-    pub fn format(&mut self) -> String {
-        let mut string = String::new();
-        for r in &mut self.runs {
-            string += &Tile::format_list(r.tiles.make_contiguous());
+    /// Plays a copy of the wild anchor as a joker
+    fn fill(&mut self, i: usize) -> bool {
+        self.fillers.push(Tile::from_ordinal(i as u8).unwrap());
+        self.take(i)
+    }
+
+    fn unfill(&mut self, i: usize, from_board: bool) {
+        self.fillers.pop();
+        self.untake(i, from_board);
+    }
+
+    fn available(&self, i: usize) -> u8 { self.board[i] + self.hand[i] }
+
+    /// The smallest real tile left to decide on. Every meld is built from the smallest real tile it contains, so
+    /// each arrangement is only reached once
+    fn anchor(&self) -> Option<usize> {
+        (0..Tile::JOKER_ORDINAL as usize).find(|&i| self.available(i) > 0)
+    }
+
+    fn counts(tiles: &[Tile]) -> TileCounts {
+        let mut counts = [0; Tile::KINDS];
+        for t in tiles { counts[t.ordinal() as usize] += 1; }
+        counts
+    }
+
+    /// Board tiles placed, then each objective in turn
+    fn score(&self, objectives: &[Objective], targets: &Targets) -> Vec<i64> {
+        let mut score = vec![self.placed as i64];
+        score.extend(objectives.iter().map(|o| o.score(self, targets)));
+        score
+    }
+
+    /// No arrangement reached from here scores higher than this in any position
+    fn bound(&self, objectives: &[Objective], targets: &Targets) -> Vec<i64> {
+        let mut bound = vec![(self.placed + self.remaining_board) as i64];
+        bound.extend(objectives.iter().map(|o| o.bound(self, targets)));
+        bound
+    }
+
+    /// Board copies are always used before hand copies - they have to be placed anyway. A joker only opens a
+    /// position, which `finish` or `fill` decides the tile for
+    fn take(&mut self, i: usize) -> bool {
+        if i == Tile::JOKER_ORDINAL as usize {
+            self.jokers_placed += 1;
+            self.slots += 1;
+            return false;
+        }
+        if self.board[i] > 0 {
+            self.board[i] -= 1;
+            self.remaining_board -= 1;
+            self.placed += 1;
+            true
+        }
+        else {
+            self.hand[i] -= 1;
+            self.remaining_hand -= 1;
+            self.played.push(Tile::from_ordinal(i as u8).unwrap());
+            false
         }
+    }
 
-        string
+    fn untake(&mut self, i: usize, from_board: bool) {
+        if i == Tile::JOKER_ORDINAL as usize {
+            self.jokers_placed -= 1;
+            self.slots -= 1;
+            return;
+        }
+        if from_board {
+            self.board[i] += 1;
+            self.remaining_board += 1;
+            self.placed -= 1;
+        }
+        else {
+            self.hand[i] += 1;
+            self.remaining_hand += 1;
+            self.played.pop();
+        }
     }
 
-    pub fn solved(&self) -> bool {
-        self.completed() == true && self.unused_hand_tiles.len() == 0
+    fn place(&mut self, meld: Meld) -> Vec<bool> {
+        let from_board = meld.tiles().iter().map(|t| self.take(t.ordinal() as usize)).collect();
+        self.melds.push(meld);
+        from_board
     }
 
-    pub fn completed(&self) -> bool {
-        self.unused_board_tiles.len() == 0 && (self.runs.last().is_none() || self.runs.last().unwrap().tiles.len() >= 3)
+    fn unplace(&mut self, from_board: Vec<bool>) {
+        let meld = self.melds.pop().unwrap();
+        for (t, b) in meld.tiles().iter().zip(from_board).rev() {
+            self.untake(t.ordinal() as usize, b);
+        }
     }
 
-    pub fn best(current: Option<SolvingState>, other: Option<SolvingState>) -> Option<SolvingState> {
-        if other.is_none() { return current; }
-        if current.is_none() { return other; }
+    /// Leaves the anchor out of every meld, in hand or (if allowed) off the board
+    fn skip(&mut self, i: usize) -> bool {
+        let tile = Tile::from_ordinal(i as u8).unwrap();
+        if self.board[i] > 0 {
+            self.board[i] -= 1;
+            self.remaining_board -= 1;
+            self.removed.push(tile);
+            true
+        }
+        else {
+            self.hand[i] -= 1;
+            self.remaining_hand -= 1;
+            self.kept.push(tile);
+            false
+        }
+    }
 
-        let other = other.unwrap();
-        let current = current.unwrap();
-        if (other.solved() && !current.solved()) || (other.completed() && !current.completed()) {
-            Some(other)
+    fn unskip(&mut self, i: usize, from_board: bool) {
+        if from_board {
+            self.board[i] += 1;
+            self.remaining_board += 1;
+            self.removed.pop();
         }
         else {
-            Some(current)
+            self.hand[i] += 1;
+            self.remaining_hand += 1;
+            self.kept.pop();
         }
     }
-}
 
-impl Clone for SolvingState {
-    #[time_graph::instrument]
-    fn clone(&self) -> Self {
-        SolvingState {
-            unused_hand_tiles: self.unused_hand_tiles.clone(),
-            unused_board_tiles: self.unused_board_tiles.clone(),
-            runs: self.runs.clone()
+    /// Every meld whose smallest real tile is the anchor, built from the tiles still available, with meld sizes and
+    /// numbers as `rules` allow
+    #[cfg_attr(feature = "profiling", time_graph::instrument)]
+    fn candidates(&self, anchor: usize, rules: &RuleSet) -> Vec<Meld> {
+        let tile = Tile::from_ordinal(anchor as u8).unwrap();
+        let number = tile.number().unwrap();
+        let jokers = self.spare_jokers(anchor);
+        let mut candidates = Vec::new();
+
+        let mut run = vec![tile];
+        self.extend_run(number + 1, jokers, rules, &mut run, &mut candidates);
+
+        let max_group = rules.max_group_size.min(Colour::ALL.len());
+        // Groups with a single real tile are the same tiles as a run with jokers, which is already a candidate
+        let others: Vec<Tile> = (anchor + Tile::NUMBERS..Tile::JOKER_ORDINAL as usize).step_by(Tile::NUMBERS)
+            .filter(|&i| self.available(i) > 0)
+            .map(|i| Tile::from_ordinal(i as u8).unwrap())
+            .collect();
+        for mask in 1..(1u32 << others.len()) {
+            let mut group = vec![tile];
+            group.extend(others.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, t)| *t));
+            let real = group.len();
+            if real > max_group { continue; }
+
+            for j in 0..=jokers.min(max_group - real) {
+                if real + j < rules.min_meld_size { continue; }
+                let mut tiles = group.clone();
+                tiles.extend(std::iter::repeat_n(Tile::Joker, j));
+                candidates.push(Meld::new_unchecked(tiles, MeldKind::Group, rules));
+            }
+        }
+
+        candidates
+    }
+
+    /// Grows a run upwards from its first tile, the anchor. Jokers only go below the anchor once the run reaches the
+    /// top number, so the same tiles aren't offered again with the jokers shuffled around
+    fn extend_run(&self, next: u8, jokers: usize, rules: &RuleSet, run: &mut Vec<Tile>, candidates: &mut Vec<Meld>) {
+        let Tile::Normal(colour, first) = run[0] else { unreachable!("runs start with the anchor") };
+        if run.len() >= rules.min_meld_size { candidates.push(Meld::new_unchecked(run.clone(), MeldKind::Run, rules)); }
+
+        if next > rules.max_number.min(Meld::MAX_NUMBER) {
+            for p in 1..=jokers.min(first as usize - 1) {
+                if run.len() + p < rules.min_meld_size { continue; }
+                let mut tiles = vec![Tile::Joker; p];
+                tiles.extend(run.iter());
+                candidates.push(Meld::new_unchecked(tiles, MeldKind::Run, rules));
+            }
+            return;
+        }
+
+        let real = Tile::Normal(colour, next);
+        if self.available(real.ordinal() as usize) > 0 {
+            run.push(real);
+            self.extend_run(next + 1, jokers, rules, run, candidates);
+            run.pop();
+        }
+        if jokers > 0 {
+            run.push(Tile::Joker);
+            self.extend_run(next + 1, jokers - 1, rules, run, candidates);
+            run.pop();
         }
     }
-}
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-enum RunType {
-    Number(u8),
-    Colour(Colour, u8, u8),
-    Unknown
+    /// Whatever is left once every real tile is decided on. Joker positions not filled by wild tiles take board
+    /// jokers, then hand jokers. Jokers can't form a meld alone
+    fn finish(&mut self, optional_board: bool) -> bool {
+        let joker = Tile::JOKER_ORDINAL as usize;
+        // A wild tile played as a joker has to have a position to fill
+        let Some(unfilled) = self.slots.checked_sub(self.fillers.len()) else { return false; };
+        let board = self.board[joker] as usize;
+        let from_board = unfilled.min(board);
+        let from_hand = unfilled - from_board;
+        if from_hand > self.hand[joker] as usize || (board > from_board && !optional_board) { return false; }
+
+        self.placed += from_board;
+        for _ in from_board..board { self.removed.push(Tile::Joker); }
+        for _ in 0..from_hand { self.played.push(Tile::Joker); }
+        for _ in from_hand..self.hand[joker] as usize { self.kept.push(Tile::Joker); }
+        self.remaining_board = 0;
+        self.remaining_hand = 0;
+        self.board[joker] = 0;
+        self.hand[joker] = 0;
+        true
+    }
+
+    fn into_solution(mut self, merge_runs: bool) -> Solution {
+        self.kept.sort();
+        self.played.sort();
+        let mut solution = Solution::new(self.melds, self.played, self.kept);
+        if merge_runs { solution.merge_runs(); }
+        solution.fill_wilds(self.fillers)
+    }
 }
 
-#[derive(Hash)]
-struct Run {
-    tiles: VecDeque<Tile>,
-    kind: RunType
+struct Search<'a> {
+    config: &'a SolverConfig,
+    targets: Targets,
+    /// Scored after the config's objectives, from `solve_with`
+    custom: Option<&'a CustomObjective<'a>>,
+    optional_board: bool,
+    best: Option<(Vec<i64>, SolvingState)>,
+    nodes: u64,
+    /// Either limit was reached
+    exhausted: bool,
+    timed_out: bool,
+    depth: usize,
+    start: Instant,
+    last_progress: Instant
 }
 
-impl Run {
-    #[time_graph::instrument]
-    pub fn new(tile: Tile) -> Run {
-        let mut tiles = VecDeque::with_capacity(3);
-        tiles.push_back(tile);
-        Run { tiles, kind: RunType::Unknown }
-    }
+impl<'a> Search<'a> {
+    /// How often, in nodes, to check whether progress is due - reading the clock every node is too slow
+    const PROGRESS_CHECK_NODES: u64 = 1024;
 
-    #[time_graph::instrument]
-    pub fn join(&self, tile: &Tile) -> (Option<Run>, Option<Run>) {
-        fn prepend(s: &Run, t: &Tile, k: RunType) -> Run {
-            let mut run = s.clone();
-            run.kind = k;
-            run.tiles.push_front(t.clone());
-            run
+    fn new(config: &'a SolverConfig, optional_board: bool) -> Search<'a> {
+        let start = Instant::now();
+        Search {
+            config,
+            targets: Targets::new(config),
+            custom: None,
+            optional_board,
+            best: None,
+            nodes: 0,
+            exhausted: false,
+            timed_out: false,
+            depth: 0,
+            start,
+            last_progress: start
         }
+    }
+
+    /// `SolvingState::bound`, with nothing ruled out for a custom objective
+    fn bound(&self, state: &SolvingState) -> Vec<i64> {
+        let mut bound = state.bound(&self.config.objectives, &self.targets);
+        if self.custom.is_some() { bound.push(i64::MAX); }
+        bound
+    }
+
+    fn score(&self, leaf: &SolvingState) -> Vec<i64> {
+        let mut score = leaf.score(&self.config.objectives, &self.targets);
+        if let Some(custom) = self.custom { score.push(custom(&leaf.melds, &leaf.kept.iter().copied().collect())); }
+        score
+    }
+
+    fn event(&self, event: SolveEvent) {
+        if let Some(on_event) = &self.config.on_event { on_event(event); }
+    }
+
+    fn honours_pins(&self, state: &SolvingState) -> bool {
+        if self.config.pinned.is_empty() { return true; }
 
-        fn append(s: &Run, t: &Tile, k: RunType) -> Run {
-            let mut run = s.clone();
-            run.kind = k;
-            run.tiles.push_back(t.clone());
-            run
+        // Groups with a single real tile are never built, being the same tiles as a run with jokers, so such a run
+        // also counts as a group
+        let max_group = self.config.rules.max_group_size.min(Colour::ALL.len());
+        let mut placed: Vec<(Tile, MeldKind, bool)> = state.melds.iter()
+            .flat_map(|m| {
+                let either = m.tiles().iter().filter(|t| !t.is_joker()).count() == 1 && m.tiles().len() <= max_group;
+                m.tiles().iter().map(move |t| (*t, m.kind(), either))
+            })
+            .collect();
+        self.config.pinned.iter().all(|(tile, kind)| match placed.iter().position(|(t, k, either)| t == tile && (k == kind || *either)) {
+            Some(i) => { placed.swap_remove(i); true },
+            None => false
+        })
+    }
+
+    fn honours_frozen(&self, state: &SolvingState) -> bool {
+        if self.config.frozen_length.is_empty() { return true; }
+
+        state.melds.iter().all(|m| {
+            let tiles: TileMultiset = m.tiles().iter().collect();
+            self.config.frozen_length.iter().all(|f| {
+                f.tiles().len() >= m.tiles().len()
+                    || !f.tiles().iter().collect::<TileMultiset>().iter().all(|(t, n)| tiles.contains_at_least(&t, n))
+            })
+        })
+    }
+
+    fn trace(&self, state: &SolvingState, decision: impl FnOnce() -> TraceDecision) {
+        let Some(on_trace) = &self.config.on_trace else { return; };
+        on_trace(TraceNode {
+            depth: self.depth,
+            decision: decision(),
+            bound: self.bound(state)
+        });
+    }
+
+    /// Takes a branch, recording it in the trace
+    fn descend(&mut self, state: &mut SolvingState) {
+        self.depth += 1;
+        self.solve_state(state);
+        self.depth -= 1;
+    }
+
+    fn run(&mut self, state: &mut SolvingState) {
+        self.event(SolveEvent::Started {
+            board: state.remaining_board,
+            hand: state.remaining_hand,
+            objectives: self.config.objectives.clone()
+        });
+        self.solve_state(state);
+        if self.timed_out {
+            self.event(SolveEvent::TimedOut { nodes: self.nodes, elapsed: self.start.elapsed() });
+        } else if self.exhausted {
+            self.event(SolveEvent::BudgetExhausted { nodes: self.nodes });
         }
+        self.event(SolveEvent::Finished { nodes: self.nodes, elapsed: self.start.elapsed(), found: self.best.is_some() });
+    }
+
+    fn report_progress(&mut self) {
+        let Some(on_progress) = &self.config.on_progress else { return; };
+        if !self.nodes.is_multiple_of(Search::PROGRESS_CHECK_NODES) { return; }
+
+        let now = Instant::now();
+        if now - self.last_progress < self.config.progress_interval { return; }
+        self.last_progress = now;
 
-        match &self.kind {
-            RunType::Number(n) => {
-                if self.tiles.len() == 4 { return (None, None); }
-                if tile.is_joker() { return (Some(append(self, tile, RunType::Number(*n))), None); }
-                if tile.number().unwrap() != *n { return (None, None) }
+        on_progress(SolveProgress {
+            nodes: self.nodes,
+            elapsed: now - self.start,
+            best_played: self.best.as_ref().map(|(_, b)| b.played.len())
+        });
+    }
 
-                for t in &self.tiles {
-                    if t.colour() == tile.colour() { return (None, None); }
-                }
+    fn solve_state(&mut self, state: &mut SolvingState) {
+        if self.timed_out || self.config.max_nodes.is_some_and(|max| self.nodes >= max) {
+            self.exhausted = true;
+            return;
+        }
+        if self.nodes.is_multiple_of(Search::PROGRESS_CHECK_NODES) && self.config.time_limit.is_some_and(|limit| self.start.elapsed() >= limit) {
+            self.exhausted = true;
+            self.timed_out = true;
+            return;
+        }
+        self.nodes += 1;
+        self.report_progress();
 
-                (Some(append(self, tile, RunType::Number(*n))), None)
+        if let Some((score, _)) = &self.best {
+            if self.bound(state) <= *score {
+                self.trace(state, || TraceDecision::Pruned);
+                return;
             }
-            RunType::Colour(c, min, max) => {
-                if tile.is_joker() {
-                    if *min > 1 && *max < 13  {
-                        return (Some(prepend(self, tile, RunType::Colour(c.clone(), min - 1, *max))),
-                                Some(append(self, tile, RunType::Colour(c.clone(), *min, max + 1))));
-                    }
-                    else if *min > 1 { return (Some(prepend(self, tile, RunType::Colour(c.clone(), min - 1, *max))), None); }
-                    else if *max < 13 { return (Some(append(self, tile, RunType::Colour(c.clone(), *min, max + 1))), None); }
-                }
-
-                if *c != tile.colour().unwrap() { (None, None) }
-                else if tile.number().unwrap() == min - 1 {
-                    (Some(prepend( self, tile, RunType::Colour(c.clone(), min - 1, *max))), None)
-                }
-                else if tile.number().unwrap() == min + 1 {
-                    (Some(append( self, tile, RunType::Colour(c.clone(), *min, max + 1))), None)
-                }
-                else { (None, None) }
+        }
+
+        if !state.slots_fillable() {
+            self.trace(state, || TraceDecision::Invalid);
+            return;
+        }
+
+        let Some(anchor) = state.anchor() else {
+            let mut leaf = state.clone();
+            if !leaf.finish(self.optional_board) || !self.honours_pins(&leaf) || !self.honours_frozen(&leaf) {
+                self.trace(state, || TraceDecision::Invalid);
+                return;
             }
-            RunType::Unknown => {
-                let mut existing = None;
-                let (mut pre_js, mut post_js) = (0, 0);
-
-                for t in &self.tiles {
-                    if t.is_joker() {
-                        if existing.is_none() { pre_js += 1; }
-                        else { post_js += 1; }
-                        continue;
-                    }
-                    existing = Some(t);
-                }
-
-                match existing {
-                    None => {
-                        if tile.is_joker() {
-                            (Some(append(self, tile, RunType::Unknown)), None)
-                        }
-                        else {
-                            (Some(prepend(self, tile, RunType::Unknown)), Some(append(self, tile, RunType::Unknown)))
-                        }
-                    },
-                    Some(existing) => {
-                        if tile.is_joker() {
-                            if existing.number().unwrap() == 1 {
-                                (Some(append(self, tile, RunType::Unknown)), None)
-                            }
-                            else if existing.number().unwrap() == 13 {
-                                (Some(prepend(self, tile, RunType::Unknown)), None)
-                            }
-                            else {
-                                (Some(prepend(self, tile, RunType::Unknown)), Some(append(self, tile, RunType::Unknown)))
-                            }
-                        }
-                        else {
-                            if tile.colour().unwrap() == existing.colour().unwrap() {
-                                if tile.number().unwrap() == existing.number().unwrap() - pre_js - 1 {
-                                    (Some(prepend(self, tile,
-                                                  RunType::Colour(tile.colour().unwrap(),
-                                                    tile.number().unwrap(),
-                                                    existing.number().unwrap() + post_js
-                                                  ))),
-                                     None)
-                                }
-                                else if tile.number().unwrap() == existing.number().unwrap() + post_js + 1 {
-                                    (Some(append(self, tile,
-                                                  RunType::Colour(tile.colour().unwrap(),
-                                                                  existing.number().unwrap() - pre_js,
-                                                                  tile.number().unwrap(),
-                                                  ))),
-                                     None)
-                                }
-                                else {
-                                    (None, None)
-                                }
-                            }
-                            else if self.tiles.len() != 4 && tile.number().unwrap() == existing.number().unwrap() {
-                                (Some(append(self, tile, RunType::Number(tile.number().unwrap()))), None)
-                            }
-                            else {
-                                (None, None)
-                            }
-                        }
-                    }
-                }
+            let score = self.score(&leaf);
+            let best = self.best.as_ref().is_none_or(|(b, _)| score > *b);
+            self.trace(state, || TraceDecision::Leaf { score: score.clone(), best });
+            if best {
+                self.event(SolveEvent::Improved { nodes: self.nodes, score: score.clone() });
+                self.best = Some((score, leaf));
             }
+            return;
+        };
+
+        for meld in state.candidates(anchor, &self.config.rules) {
+            self.trace(state, || TraceDecision::Place(meld.clone()));
+            let from_board = state.place(meld);
+            self.descend(state);
+            state.unplace(from_board);
         }
-    }
-}
 
-impl Clone for Run {
-    #[time_graph::instrument]
-    fn clone(&self) -> Self {
-        Run {
-            tiles: self.tiles.clone(),
-            kind: self.kind.clone()
+        if state.board[anchor] == 0 || self.optional_board {
+            self.trace(state, || TraceDecision::Skip(Tile::from_ordinal(anchor as u8).unwrap()));
+            let from_board = state.skip(anchor);
+            self.descend(state);
+            state.unskip(anchor, from_board);
+        }
+
+        if state.wild[anchor] {
+            self.trace(state, || TraceDecision::Fill(Tile::from_ordinal(anchor as u8).unwrap()));
+            let from_board = state.fill(anchor);
+            self.descend(state);
+            state.unfill(anchor, from_board);
         }
     }
 }
 
-pub fn solve(state: &State) -> SolvingState {
-    let mut visited = HashSet::new();
-    solve_state(SolvingState::from_state(state), &mut visited).unwrap()
+/// Whether the deck has enough copies of every tile in the position
+fn within_deck(state: &State, rules: &RuleSet) -> bool {
+    let deck = rules.deck();
+    state.into_iter().collect::<TileMultiset>().iter().all(|(t, n)| deck.contains_at_least(&t, n))
 }
 
-#[time_graph::instrument]
-fn hash_check(state: &SolvingState, visited_hashes: &mut HashSet<u64>) -> bool {
-    let mut hasher = DefaultHasher::new();
-    state.hash(&mut hasher);
-    visited_hashes.insert(hasher.finish())
+/// Places every board tile while playing as many hand tiles as possible, or `None` if the board can't be arranged.
+/// Jokers need a real tile in each meld, so a board of only jokers is `None` unless the hand supplies one
+pub fn solve(state: &State) -> Option<Solution> {
+    solve_with_config(state, &SolverConfig::default())
 }
 
-pub fn solve_state(state: SolvingState, visited_hashes: &mut HashSet<u64>) -> Option<SolvingState> {
-    if !hash_check(&state, visited_hashes) { return None; }
+pub fn solve_with_config(state: &State, config: &SolverConfig) -> Option<Solution> {
+    search_with_config(state, config).0
+}
 
-    if state.unused_board_tiles.len() == 0 && state.unused_hand_tiles.len() == 0 { return Some(state); }
+/// Turns the runs with a single real tile that `honours_pins` let stand in for a pinned group into that group
+fn regroup_pinned(melds: &mut [Meld], config: &SolverConfig) {
+    let max_group = config.rules.max_group_size.min(Colour::ALL.len());
+    let mut claimed = vec![false; melds.len()];
+    for (tile, _) in config.pinned.iter().filter(|(_, k)| *k == MeldKind::Group) {
+        if let Some(i) = (0..melds.len()).find(|&i| !claimed[i] && melds[i].kind() == MeldKind::Group && melds[i].tiles().contains(tile)) {
+            claimed[i] = true;
+            continue;
+        }
+        let Some(i) = (0..melds.len()).find(|&i| {
+            let m = &melds[i];
+            !claimed[i] && m.tiles().contains(tile) && m.tiles().len() <= max_group && m.tiles().iter().filter(|t| !t.is_joker()).count() == 1
+        }) else { continue; };
+        let mut tiles = vec![*tile];
+        tiles.resize(melds[i].tiles().len(), Tile::Joker);
+        melds[i] = Meld::new_unchecked(tiles, MeldKind::Group, &config.rules);
+        claimed[i] = true;
+    }
+}
 
-    let mut best = None;
+/// Why `solve_budgeted` has no complete answer
+#[derive(Debug)]
+pub enum SolveError {
+    /// The whole search ran, and the board can't be arranged
+    NoArrangement,
+    /// There are jokers on the board but no real tile anywhere, and every meld needs one
+    JokersOnly,
+    /// `SolverConfig::max_nodes` ran out first. `best` is the best arrangement found before then, if any
+    BudgetExhausted { best: Option<Solution> },
+    /// `SolverConfig::time_limit` ran out first, with the best arrangement found before then, if any
+    TimedOut { best: Option<Solution> }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::NoArrangement => write!(f, "Board can't be arranged into valid melds"),
+            SolveError::JokersOnly => write!(f, "Cannot form valid sets from jokers alone"),
+            SolveError::BudgetExhausted { best: Some(_) } => write!(f, "Ran out of nodes before the best arrangement was proven"),
+            SolveError::BudgetExhausted { best: None } => write!(f, "Ran out of nodes before finding any arrangement"),
+            SolveError::TimedOut { best: Some(_) } => write!(f, "Ran out of time before the best arrangement was proven"),
+            SolveError::TimedOut { best: None } => write!(f, "Ran out of time before finding any arrangement")
+        }
+    }
+}
 
-    for (mut j, t) in state.unused_board_tiles.iter().chain(state.unused_hand_tiles.iter()).enumerate() {
-        let mut board = true;
-        if j >= state.unused_board_tiles.len() { j -= state.unused_board_tiles.len(); board = false; }
+/// `solve_with_config`, but telling a board that can't be arranged apart from one of only jokers, or from a search
+/// cut short by `SolverConfig::max_nodes` or `SolverConfig::time_limit`. Only a finished search is `Ok`
+// The error carries the best solution found, which is the point of it
+#[allow(clippy::result_large_err)]
+pub fn solve_budgeted(state: &State, config: &SolverConfig) -> Result<Solution, SolveError> {
+    if !state.board.is_empty() && state.into_iter().all(|t| t.is_joker()) { return Err(SolveError::JokersOnly); }
+    match search_with_config(state, config) {
+        (Some(solution), false, _) => Ok(solution),
+        (None, false, _) => Err(SolveError::NoArrangement),
+        (best, true, false) => Err(SolveError::BudgetExhausted { best }),
+        (best, true, true) => Err(SolveError::TimedOut { best })
+    }
+}
 
-        for (i, r) in state.runs.iter().enumerate() {
-            let new_runs = r.join(t);
+/// The best solution found, whether the node budget or time limit ran out, and whether it was the time limit
+fn search_with_config(state: &State, config: &SolverConfig) -> (Option<Solution>, bool, bool) {
+    #[cfg(debug_assertions)]
+    state.assert_sorted();
+    if !within_deck(state, &config.rules) { return (None, false, false); }
+    let mut solving = SolvingState::from_state(state).with_rules(&config.rules);
 
-            if new_runs.0.is_some() {
-                if let Some(value) = creation(&state, visited_hashes, &mut best, j, &mut board, i, new_runs) {
-                    return value;
-                }
-            }
+    let mut search = Search::new(config, false);
+    search.run(&mut solving);
+    // Merging could grow a frozen meld
+    let append_only = config.objectives.contains(&Objective::AppendOnly);
+    let merge_runs = !config.objectives.contains(&Objective::MinimalRearrangement) && !append_only && config.frozen_length.is_empty();
+    let mut solution = search.best.map(|(_, mut b)| {
+        let appended = append_only.then(|| append::lay_out_appended(&config.board_melds, &mut b.melds, &config.rules));
+        regroup_pinned(&mut b.melds, config);
+        let mut solution = b.into_solution(merge_runs);
+        solution.appended = appended;
+        solution
+    });
+    if let Some(solution) = &mut solution {
+        solution.budget_exhausted = search.exhausted;
+        solution.joker_labels = state.solution_labels(solution.played());
+        if config.objectives.contains(&Objective::MinimalRearrangement) {
+            solution.disturbed_melds = Some(solution.disturbed(&config.board_melds));
         }
+    }
+    debug_assert!(solution.as_ref().is_none_or(|s| s.verify_with_rules(state, &config.rules).is_ok()));
+    (solution, search.exhausted, search.timed_out)
+}
 
-        if state.runs.last().is_none() || state.runs.last().unwrap().tiles.len() >= 3 {
-            if let Some(value) = creation_two(&state, visited_hashes, &mut best, j, t, &mut board) {
-                return value;
-            }
+/// Places every board tile while maximising `objective`, given the melds and the hand tiles left over. Nothing
+/// about the objective is known in advance, so every arrangement is scored and this is much slower than `solve`.
+/// Runs aren't merged, so the solution's melds are the ones that were scored
+pub fn solve_with(state: &State, objective: &dyn Fn(&[Meld], &Hand) -> i64) -> Option<Solution> {
+    let config = SolverConfig { objectives: Vec::new(), ..SolverConfig::default() };
+    if !within_deck(state, &config.rules) { return None; }
+    let mut search = Search::new(&config, false);
+    search.custom = Some(objective);
+    search.run(&mut SolvingState::from_state(state));
+    let mut solution = search.best.map(|(_, b)| b.into_solution(false));
+    if let Some(solution) = &mut solution { solution.joker_labels = state.solution_labels(solution.played()); }
+    debug_assert!(solution.as_ref().is_none_or(|s| s.verify(state).is_ok()));
+    solution
+}
+
+/// Why `solve_constrained` found nothing
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConstraintError {
+    /// The board can't be arranged at all
+    NoArrangement,
+    Pins,
+    FrozenLength,
+    /// Either constraint can be met, but not both at once
+    PinsAndFrozenLength
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintError::NoArrangement => write!(f, "Board can't be arranged into valid melds"),
+            ConstraintError::Pins => write!(f, "The pinned tiles can't all go into melds of their kind"),
+            ConstraintError::FrozenLength => write!(f, "The board can't be arranged without growing a frozen meld"),
+            ConstraintError::PinsAndFrozenLength => write!(f, "The pins can only be honoured by growing a frozen meld")
         }
     }
+}
 
-    best.map_or(Some(state), |b| Some(b))
+/// `solve_with_config`, but working out which constraints are to blame when there's no solution. That takes up
+/// to three more solves
+pub fn solve_constrained(state: &State, config: &SolverConfig) -> Result<Solution, ConstraintError> {
+    if let Some(solution) = solve_with_config(state, config) { return Ok(solution); }
+
+    let relaxed = |pinned: bool, frozen: bool| {
+        let config = SolverConfig {
+            pinned: if pinned { config.pinned.clone() } else { Vec::new() },
+            frozen_length: if frozen { config.frozen_length.clone() } else { Vec::new() },
+            ..config.clone()
+        };
+        solve_with_config(state, &config).is_some()
+    };
+    if !relaxed(false, false) { return Err(ConstraintError::NoArrangement); }
+    let pins = config.pinned.is_empty() || relaxed(true, false);
+    let frozen = config.frozen_length.is_empty() || relaxed(false, true);
+    Err(match (pins, frozen) {
+        (false, _) => ConstraintError::Pins,
+        (true, false) => ConstraintError::FrozenLength,
+        (true, true) => ConstraintError::PinsAndFrozenLength
+    })
+}
+
+/// Rearranges the board so that a copy of `target` can be taken back into the hand, or `None` if that's impossible.
+/// Copies played this turn can't be taken back
+pub fn solve_to_free(state: &State, target: Tile) -> Option<Solution> {
+    if state.board.iter().filter(|t| **t == target).count() <= state.played_this_turn().count(&target) { return None; }
+    let mut board = state.board.clone();
+    if !board.remove(&target) { return None; }
+
+    let mut solution = solve(&State::from_parts(board, state.hand.clone()))?;
+    let (Ok(i) | Err(i)) = solution.hand.binary_search(&target);
+    solution.hand.insert(i, target);
+    Some(solution)
 }
 
-#[time_graph::instrument]
-fn creation_two(state: &SolvingState, visited_hashes: &mut HashSet<u64>, best: &mut Option<SolvingState>, j: usize, t: &Tile, board: &mut bool) -> Option<Option<SolvingState>> {
-    let mut new_state = state.clone();
-    new_state.runs.push(Run::new(t.clone()));
-    if *board {
-        new_state.unused_board_tiles.remove(j);
-    } else {
-        new_state.unused_hand_tiles.remove(j);
+/// Keeps as many board tiles as possible, returning the melds formed and the tiles that had to be left out
+pub fn solve_board_subset(state: &State) -> (Vec<Meld>, Vec<Tile>) {
+    let mut solving = SolvingState::from_state(state);
+    solving.hand = [0; Tile::KINDS];
+    solving.remaining_hand = 0;
+
+    let config = SolverConfig::default();
+    let mut search = Search::new(&config, true);
+    search.run(&mut solving);
+    let (_, best) = search.best.unwrap();
+    let mut removed = best.removed.clone();
+    removed.sort();
+    (best.into_solution(true).melds().to_vec(), removed)
+}
+
+/// Up to `max` distinct ways of arranging the whole board into melds under `rules`, in the order the search finds
+/// them. Wild colours aren't used
+pub fn board_partitions(state: &State, max: usize, rules: &RuleSet) -> Vec<Vec<Meld>> {
+    let mut solving = SolvingState::from_state(state);
+    solving.hand = [0; Tile::KINDS];
+    solving.remaining_hand = 0;
+
+    let mut partitions = Vec::new();
+    collect_partitions(&mut solving, max, rules, &mut HashSet::new(), &mut partitions);
+    partitions
+}
+
+fn collect_partitions(state: &mut SolvingState, max: usize, rules: &RuleSet, seen: &mut HashSet<Vec<Meld>>, partitions: &mut Vec<Vec<Meld>>) {
+    if partitions.len() >= max { return; }
+
+    let Some(anchor) = state.anchor() else {
+        if state.slots != state.board[Tile::JOKER_ORDINAL as usize] as usize { return; }
+        // Copies of a tile can be picked in either order, reaching the same melds more than once
+        let mut melds = state.melds.clone();
+        melds.sort_by(|a, b| a.tiles().cmp(b.tiles()));
+        if seen.insert(melds.clone()) { partitions.push(melds); }
+        return;
+    };
+
+    for meld in state.candidates(anchor, rules) {
+        let from_board = state.place(meld);
+        collect_partitions(state, max, rules, seen, partitions);
+        state.unplace(from_board);
     }
-    *best = SolvingState::best(best.take(), solve_state(new_state, visited_hashes));
-    if best.is_some() && best.as_ref().unwrap().solved() { return Some(best.take()); }
-    None
 }
 
-#[time_graph::instrument]
-fn creation(state: &SolvingState, visited_hashes: &mut HashSet<u64>, best: &mut Option<SolvingState>, j: usize, board: &mut bool, i: usize, new_runs: (Option<Run>, Option<Run>)) -> Option<Option<SolvingState>> {
-    let mut new_state = state.clone();
-    new_state.runs[i] = new_runs.0.unwrap();
-    if new_runs.1.is_some() {
-        new_state.runs.push(new_runs.1.unwrap());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{position, tiles};
+
+    fn wild_blue() -> SolverConfig {
+        SolverConfig { rules: RuleSet { wild_colours: vec![Colour::Blue], ..RuleSet::standard() }, ..SolverConfig::default() }
+    }
+
+    #[test]
+    fn plain_run_in_wild_colour() {
+        for board in ["b1 b2 b3", "b4 b5 b6 b7"] {
+            let state = position(board, "");
+            let solution = solve_with_config(&state, &wild_blue()).expect(board);
+            assert_eq!(solution.melds().len(), 1);
+            assert!(Meld::new_with_rules(solution.melds()[0].tiles().to_vec(), &wild_blue().rules).is_ok());
+        }
     }
-    if *board {
-        new_state.unused_board_tiles.remove(j);
-    } else {
-        new_state.unused_hand_tiles.remove(j);
+
+    #[test]
+    fn group_pin_on_one_real_tile() {
+        let config = SolverConfig { pinned: vec![(Tile::Normal(Colour::Red, 5), MeldKind::Group)], ..SolverConfig::default() };
+        let solution = solve_with_config(&position("r5 j j", ""), &config).unwrap();
+        assert_eq!(solution.melds().iter().map(Meld::kind).collect::<Vec<_>>(), [MeldKind::Group]);
     }
-    *best = SolvingState::best(best.take(), solve_state(new_state, visited_hashes));
-    if best.is_some() && best.as_ref().unwrap().solved() { return Some(best.take()); }
-    None
-}
\ No newline at end of file
+
+    #[test]
+    fn jokers_alone() {
+        assert!(solve(&position("j j j", "")).is_none());
+        assert!(matches!(solve_budgeted(&position("j j j", ""), &SolverConfig::default()), Err(SolveError::JokersOnly)));
+        assert!(matches!(solve_budgeted(&position("j j", "j"), &SolverConfig::default()), Err(SolveError::JokersOnly)));
+        assert!(matches!(solve_budgeted(&position("r5 j", ""), &SolverConfig::default()), Err(SolveError::NoArrangement)));
+        assert!(solve(&position("j j", "j")).is_none());
+        assert!(board_partitions(&position("j j j", ""), 10, &RuleSet::standard()).is_empty());
+        assert_eq!(solve_board_subset(&position("j j j", "")), (Vec::new(), vec![Tile::Joker; 3]));
+
+        // With a deck holding that many jokers, one real tile is enough
+        let config = SolverConfig { rules: RuleSet { jokers: 4, ..RuleSet::standard() }, ..SolverConfig::default() };
+        assert!(solve_with_config(&position("j j j j", ""), &config).is_none());
+        let state = position("j j j", "r5");
+        assert!(solve_with_config(&state, &config).unwrap().verify(&state).is_ok());
+    }
+
+    #[test]
+    fn insertion_order_doesnt_matter() {
+        let board = "r1 r2 r3 b5 y5 x5 j r7 r8 b9 b10 b11";
+        let hand = "r4 r9 b12 y5 x6 j";
+        let forwards = position(board, hand);
+        let mut backwards = State::new();
+        for t in tiles(board).into_iter().rev() { backwards.add_to_board(t); }
+        for t in tiles(hand).into_iter().rev() { backwards.add_to_hand(t); }
+
+        let (a, b) = (solve(&forwards).unwrap(), solve(&backwards).unwrap());
+        assert_eq!(a.melds(), b.melds());
+        assert_eq!(a.played(), b.played());
+        assert_eq!(a.hand(), b.hand());
+    }
+
+    #[test]
+    fn meld_sizes_follow_rules() {
+        let small = RuleSet { max_group_size: 3, ..RuleSet::standard() };
+        let four = position("r5 b5 y5 x5", "");
+        assert_eq!(board_partitions(&four, 10, &RuleSet::standard()).len(), 1);
+        assert!(board_partitions(&four, 10, &small).is_empty());
+        let with_joker = position("r5 b5 y5", "j");
+        assert!(with_joker.groups_for_with_rules(5, &small).iter().all(|g| g.tiles().len() <= 3));
+        assert!(with_joker.groups_for(5).iter().any(|g| g.tiles().len() == 4));
+
+        let long = RuleSet { min_meld_size: 4, ..RuleSet::standard() };
+        assert!(board_partitions(&position("r1 r2 r3", ""), 10, &long).is_empty());
+        let config = SolverConfig { rules: long, ..SolverConfig::default() };
+        assert!(solve_with_config(&position("r1 r2 r3", ""), &config).is_none());
+        assert!(solve_with_config(&position("r1 r2 r3 r4", ""), &config).is_some());
+
+        // The joker can only go below a run that reaches the top number
+        let config = SolverConfig { rules: RuleSet { max_number: 10, ..RuleSet::standard() }, ..SolverConfig::default() };
+        let solution = solve_with_config(&position("r9 r10 j", ""), &config).unwrap();
+        assert_eq!(solution.melds()[0].tiles(), [Tile::Joker, Tile::Normal(Colour::Red, 9), Tile::Normal(Colour::Red, 10)]);
+    }
+
+    #[test]
+    fn short_melds_under_custom_rules() {
+        let rules = RuleSet { min_meld_size: 2, ..RuleSet::standard() };
+        let state = position("r1 r2 b7 y7", "");
+        let solution = solve_with_config(&state, &SolverConfig { rules: rules.clone(), ..SolverConfig::default() }).unwrap();
+        assert_eq!(solution.melds().len(), 2);
+        assert!(solution.verify_with_rules(&state, &rules).is_ok());
+        assert!(solution.verify(&state).is_err());
+    }
+
+    #[test]
+    fn budget_exhausted_before_any_leaf() {
+        let state = crate::examples::sample();
+        let config = SolverConfig { max_nodes: Some(1), ..SolverConfig::default() };
+        assert!(solve_with_config(&state, &config).is_none());
+        assert!(matches!(solve_budgeted(&state, &config), Err(SolveError::BudgetExhausted { best: None })));
+        let cut_off = (2..).map(|n| SolverConfig { max_nodes: Some(n), ..SolverConfig::default() })
+            .find(|c| solve_with_config(&state, c).is_some())
+            .unwrap();
+        assert!(matches!(solve_budgeted(&state, &cut_off), Err(SolveError::BudgetExhausted { best: Some(_) })));
+
+        assert!(solve_budgeted(&state, &SolverConfig::default()).is_ok());
+        assert!(matches!(solve_budgeted(&crate::examples::unsolvable(), &SolverConfig::default()), Err(SolveError::NoArrangement)));
+    }
+
+    #[test]
+    fn reports_disturbed_melds() {
+        let state = position("r1 r2 r3 b5 y5 x5", "r4");
+        let mut config = SolverConfig {
+            board_melds: vec![Meld::run(tiles("r1 r2 r3")).unwrap(), Meld::group(tiles("b5 y5 x5")).unwrap()],
+            ..SolverConfig::default()
+        };
+        assert_eq!(solve_with_config(&state, &config).unwrap().disturbed_melds(), None);
+
+        config.objectives = vec![Objective::MaxTilesPlayed, Objective::MinimalRearrangement];
+        let solution = solve_with_config(&state, &config).unwrap();
+        assert_eq!(solution.played(), tiles("r4"));
+        assert_eq!(solution.disturbed_melds(), Some(1));
+    }
+
+    #[test]
+    fn time_limit() {
+        let state = crate::examples::sample();
+        let config = SolverConfig { time_limit: Some(Duration::ZERO), ..SolverConfig::default() };
+        assert!(matches!(solve_budgeted(&state, &config), Err(SolveError::TimedOut { best: None })));
+        let config = SolverConfig { time_limit: Some(Duration::from_secs(60)), ..SolverConfig::default() };
+        assert!(solve_budgeted(&state, &config).is_ok());
+    }
+
+    #[test]
+    fn tiles_outside_universe() {
+        let universe = [Colour::Red].iter().flat_map(|c| (1..=3).map(|n| Tile::Normal(*c, n))).collect();
+        let config = SolverConfig { rules: RuleSet::with_universe(universe), ..SolverConfig::default() };
+        assert!(solve_with_config(&position("r1 r2 r3", ""), &config).is_some());
+        assert!(solve_with_config(&position("x5 x6 x7", ""), &config).is_none());
+    }
+
+    #[test]
+    fn wild_tiles_as_themselves_and_as_jokers() {
+        // b9 can only fit as a joker, while b1 b2 b3 need to be themselves
+        let state = position("b1 b2 b3 r5 r6 b9", "");
+        let solution = solve_with_config(&state, &wild_blue()).unwrap();
+        assert!(solution.verify(&state).is_ok());
+        assert_eq!(solution.melds().len(), 2);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{Colour, Solution, Tile};
+
+const TILE_WIDTH: usize = 40;
+const TILE_HEIGHT: usize = 56;
+const GAP: usize = 6;
+
+/// The number colours printed on real tiles
+fn palette(colour: Colour) -> &'static str {
+    match colour {
+        Colour::Red => "#d62828",
+        Colour::Blue => "#1d4e89",
+        Colour::Yellow => "#f4a300",
+        Colour::Black => "#1a1a1a"
+    }
+}
+
+impl Solution {
+    /// Draws each meld as a row of tiles. Jokers are labelled with the tile they stand for
+    pub fn to_svg(&self) -> String {
+        let longest = self.melds().iter().map(|m| m.tiles().len()).max().unwrap_or(0);
+        let width = GAP + longest * (TILE_WIDTH + GAP);
+        let height = GAP + self.melds().len() * (TILE_HEIGHT + GAP);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+        );
+        for (row, meld) in self.melds().iter().enumerate() {
+            let y = GAP + row * (TILE_HEIGHT + GAP);
+            for (column, resolved) in meld.resolve().iter().enumerate() {
+                let x = GAP + column * (TILE_WIDTH + GAP);
+                svg += &format!(
+                    "  <rect x=\"{x}\" y=\"{y}\" width=\"{TILE_WIDTH}\" height=\"{TILE_HEIGHT}\" rx=\"6\" fill=\"#fdf6e3\" stroke=\"#8a8070\"/>\n"
+                );
+
+                let (cx, cy) = (x + TILE_WIDTH / 2, y + TILE_HEIGHT / 2);
+                let colour = palette(resolved.colour());
+                match resolved.tile() {
+                    Tile::Normal(_, n) => svg += &format!(
+                        "  <text x=\"{cx}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"22\" font-weight=\"bold\" text-anchor=\"middle\" fill=\"{colour}\">{n}</text>\n",
+                        cy + 8
+                    ),
+                    Tile::Joker => svg += &format!(
+                        "  <text x=\"{cx}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"22\" text-anchor=\"middle\" fill=\"{colour}\">\u{263A}</text>\n  <text x=\"{cx}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"10\" text-anchor=\"middle\" fill=\"{colour}\">{}{}</text>\n",
+                        cy + 2, y + TILE_HEIGHT - 6, resolved.colour().get_char(), resolved.number()
+                    )
+                }
+            }
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
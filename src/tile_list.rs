@@ -0,0 +1,95 @@
+use std::collections::{vec_deque, VecDeque};
+use std::ops::Deref;
+use crate::Tile;
+
+/// Defines a tile list that is always sorted, so looking tiles up with `binary_search` stays valid. Reading goes
+/// through `Deref` to the underlying deque; all mutation goes through methods that keep the order. Any jokers
+/// are always at the back
+macro_rules! sorted_tile_list {
+    ($name:ident) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Default, Hash)]
+        pub struct $name(VecDeque<Tile>);
+
+        impl $name {
+            pub fn new() -> $name { $name(VecDeque::new()) }
+
+            pub fn with_capacity(capacity: usize) -> $name { $name(VecDeque::with_capacity(capacity)) }
+
+            pub fn insert(&mut self, tile: Tile) {
+                debug_assert!(self.is_sorted(), concat!(stringify!($name), " is out of order"));
+                let (Ok(i) | Err(i)) = self.0.binary_search(&tile);
+                self.0.insert(i, tile);
+            }
+
+            /// Sorts the new tiles once and merges them in, rather than inserting them one at a time
+            pub fn extend(&mut self, tiles: impl IntoIterator<Item=Tile>) {
+                let mut tiles: Vec<Tile> = tiles.into_iter().collect();
+                if tiles.is_empty() { return; }
+                tiles.sort();
+                // Keeps any capacity reserved up front
+                if self.0.is_empty() {
+                    self.0.extend(tiles);
+                    return;
+                }
+
+                let mut merged = VecDeque::with_capacity(self.0.len() + tiles.len());
+                let mut tiles = tiles.into_iter().peekable();
+                for t in self.0.drain(..) {
+                    while let Some(n) = tiles.next_if(|n| *n < t) { merged.push_back(n); }
+                    merged.push_back(t);
+                }
+                merged.extend(tiles);
+
+                self.0 = merged;
+                debug_assert!(self.is_sorted(), concat!(stringify!($name), " is out of order"));
+            }
+
+            /// Removes one copy of the tile, returning whether there was one
+            pub fn remove(&mut self, tile: &Tile) -> bool {
+                debug_assert!(self.is_sorted(), concat!(stringify!($name), " is out of order"));
+                match self.0.binary_search(tile) {
+                    Ok(i) => { self.0.remove(i); true },
+                    Err(_) => false
+                }
+            }
+
+            /// Whether the order that lookups rely on still holds, which every method here keeps
+            pub fn is_sorted(&self) -> bool { self.0.iter().is_sorted() }
+
+            pub fn clear(&mut self) { self.0.clear() }
+
+            pub fn make_contiguous(&mut self) -> &[Tile] { self.0.make_contiguous() }
+        }
+
+        impl Deref for $name {
+            type Target = VecDeque<Tile>;
+
+            fn deref(&self) -> &Self::Target { &self.0 }
+        }
+
+        impl IntoIterator for $name {
+            type Item = Tile;
+            type IntoIter = vec_deque::IntoIter<Tile>;
+
+            fn into_iter(self) -> Self::IntoIter { self.0.into_iter() }
+        }
+
+        impl<'a> IntoIterator for &'a $name {
+            type Item = &'a Tile;
+            type IntoIter = vec_deque::Iter<'a, Tile>;
+
+            fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+        }
+
+        impl FromIterator<Tile> for $name {
+            fn from_iter<T: IntoIterator<Item=Tile>>(iter: T) -> Self {
+                let mut list = $name::new();
+                list.extend(iter);
+                list
+            }
+        }
+    };
+}
+
+sorted_tile_list!(Board);
+sorted_tile_list!(Hand);